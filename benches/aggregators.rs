@@ -0,0 +1,90 @@
+//! Measures the cost of the optional statistics: the same random rows of the 413 station names
+//! are inserted into a `MyPHFMap` of each aggregator, and then a second such map is merged in.
+
+use std::{hint::black_box, time::Instant};
+
+use one_billion_row_challange::{
+    histogram::{HistogramEntry, Percentile},
+    my_hashmap::{Aggregator, Decimal, StationEntry, StatsEntry},
+    my_phf::{MyPHFMap, Phf},
+    sketch::{SketchEntry, SketchOptions},
+    station_names::STATION_NAMES,
+};
+
+const ROWS: usize = 1 << 24;
+const MARGIN: usize = 32;
+
+struct XorShift(u64);
+
+impl XorShift {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+}
+
+fn bench<E: Aggregator>(
+    label: &str,
+    phf: &Phf,
+    options: &E::Options,
+    rows: &[(&[u8], E::Measurement)],
+) {
+    let fill = || {
+        let mut map = MyPHFMap::<E>::new(phf, options);
+        for &(name, measurement) in rows {
+            map.insert_measurement(black_box(name), measurement);
+        }
+        map
+    };
+    let start = Instant::now();
+    let mut map = fill();
+    let insert = start.elapsed();
+    let other_map = fill();
+    let start = Instant::now();
+    map.merge_maps(black_box(other_map));
+    let merge = start.elapsed();
+    black_box(map);
+    println!(
+        "  {label:<16}{:>8.2} ns/row{:>10.1} us/merge",
+        insert.as_nanos() as f64 / rows.len() as f64,
+        merge.as_nanos() as f64 / 1000.0
+    );
+}
+
+fn main() {
+    let mut rng = XorShift(0x9E3779B97F4A7C15);
+    // every name is followed by the 32 readable bytes `MyPHFMap` compares at once
+    let buffers: Vec<Vec<u8>> = STATION_NAMES
+        .iter()
+        .map(|name| {
+            let mut buffer = name.to_vec();
+            buffer.resize(name.len() + MARGIN, b';');
+            buffer
+        })
+        .collect();
+    let rows: Vec<(&[u8], i32)> = (0..ROWS)
+        .map(|_| {
+            let index = rng.next() as usize % buffers.len();
+            let name = &buffers[index][..STATION_NAMES[index].len()];
+            (name, (rng.next() % 1999) as i32 - 999)
+        })
+        .collect();
+    let phf = Phf::station_names();
+
+    println!("{} keys:", STATION_NAMES.len());
+    let percentiles: Vec<Percentile> = ["50", "95", "99"].map(|p| p.parse().unwrap()).into();
+    bench::<StationEntry>("StationEntry", &phf, &(), &rows);
+    bench::<StatsEntry>("StatsEntry", &phf, &(), &rows);
+    bench::<HistogramEntry>("HistogramEntry", &phf, &percentiles, &rows);
+    let options = SketchOptions::new(0.01, percentiles);
+    let decimal_rows: Vec<(&[u8], Decimal)> = rows
+        .iter()
+        .map(|&(name, tenths)| {
+            let value = tenths as f64 / 10.0;
+            (name, Decimal { value, decimals: 1 })
+        })
+        .collect();
+    bench::<SketchEntry>("SketchEntry", &phf, &options, &decimal_rows);
+}
@@ -0,0 +1,122 @@
+//! Compares `MyHashMap`, `MySwissHashMap` and `FxHashMap` on the 413 station names of the
+//! challenge and on 10,000 generated names, inserting the same random sequence of rows into each.
+#![allow(dead_code)]
+
+use std::{hint::black_box, time::Instant};
+
+use rustc_hash::FxHashMap;
+
+// `MySwissHashMap` is not part of the library, and expects `MyHashMap` next to it
+mod my_hashmap {
+    pub use one_billion_row_challange::my_hashmap::*;
+}
+#[path = "../src/my_swiss_hashmap.rs"]
+mod my_swiss_hashmap;
+
+use my_hashmap::{Aggregator, MyHashMap, StationEntry, StationName};
+use my_swiss_hashmap::MySwissHashMap;
+use one_billion_row_challange::station_names::STATION_NAMES;
+
+const ROWS: usize = 1 << 24;
+const MARGIN: usize = 32;
+
+struct XorShift(u64);
+
+impl XorShift {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+}
+
+/// Lays the names out the way they appear in the file, so `StationName` can read past their end.
+fn names_buffer(names: &[Vec<u8>]) -> Vec<u8> {
+    let mut buffer = Vec::new();
+    for name in names {
+        buffer.extend_from_slice(name);
+        buffer.push(b';');
+    }
+    buffer.resize(buffer.len() + MARGIN, 0);
+    buffer
+}
+
+fn station_names(buffer: &[u8], names: &[Vec<u8>]) -> Vec<StationName> {
+    let mut offset = 0;
+    names
+        .iter()
+        .map(|name| {
+            let station_name = StationName {
+                ptr: unsafe { buffer.as_ptr().add(offset) },
+                len: name.len() as u8,
+            };
+            offset += name.len() + 1;
+            station_name
+        })
+        .collect()
+}
+
+fn generated_names(count: usize, rng: &mut XorShift) -> Vec<Vec<u8>> {
+    let mut names = FxHashMap::default();
+    while names.len() < count {
+        let len = 3 + rng.next() as usize % 22;
+        let name: Vec<u8> = (0..len).map(|_| b'a' + (rng.next() % 26) as u8).collect();
+        names.insert(name, ());
+    }
+    names.into_keys().collect()
+}
+
+fn bench(label: &str, rows: &[(StationName, i32)], mut insert: impl FnMut(StationName, i32)) {
+    let start = Instant::now();
+    for &(name, measurement) in rows {
+        insert(black_box(name), measurement);
+    }
+    let elapsed = start.elapsed();
+    println!(
+        "  {label:<16}{:>8.2} ns/row",
+        elapsed.as_nanos() as f64 / rows.len() as f64
+    );
+}
+
+fn bench_names(names: &[Vec<u8>], rng: &mut XorShift) {
+    println!("{} keys:", names.len());
+    let buffer = names_buffer(names);
+    let station_names = station_names(&buffer, names);
+    let rows: Vec<(StationName, i32)> = (0..ROWS)
+        .map(|_| {
+            let name = station_names[rng.next() as usize % station_names.len()];
+            (name, (rng.next() % 1999) as i32 - 999)
+        })
+        .collect();
+
+    let mut my_hashmap = MyHashMap::<StationEntry>::new(&());
+    bench("MyHashMap", &rows, |name, measurement| {
+        my_hashmap.insert_measurement(name, measurement)
+    });
+    assert_eq!(my_hashmap.iter().count(), names.len());
+
+    let mut swiss_hashmap = MySwissHashMap::new();
+    bench("MySwissHashMap", &rows, |name, measurement| {
+        swiss_hashmap.insert_measurement(name, measurement)
+    });
+    assert_eq!(swiss_hashmap.iter().count(), names.len());
+
+    let mut fx_hashmap =
+        FxHashMap::<StationName, StationEntry>::with_capacity_and_hasher(1024, Default::default());
+    bench("FxHashMap", &rows, |name, measurement| {
+        fx_hashmap
+            .entry(name)
+            .or_insert(StationEntry::EMPTY)
+            .add_measurement(measurement);
+    });
+    assert_eq!(fx_hashmap.len(), names.len());
+}
+
+fn main() {
+    let mut rng = XorShift(0x9E3779B97F4A7C15);
+    let names: Vec<Vec<u8>> = STATION_NAMES.iter().map(|name| name.to_vec()).collect();
+    bench_names(&names, &mut rng);
+    let names = generated_names(10_000, &mut rng);
+    bench_names(&names, &mut rng);
+}
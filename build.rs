@@ -0,0 +1,58 @@
+use std::{env, fmt::Write, fs, path::Path};
+
+// only the modulo hash is searched here, the other families are searched by `find-phf`
+#[allow(dead_code)]
+#[path = "src/phf_search.rs"]
+mod phf_search;
+
+const NAMES_FILE: &str = "station_names.txt";
+
+fn main() {
+    println!("cargo::rerun-if-changed={NAMES_FILE}");
+    println!("cargo::rerun-if-changed=src/phf_search.rs");
+    let names_file = fs::read_to_string(NAMES_FILE).expect("station_names.txt not found");
+    let mut names: Vec<&str> = names_file.lines().filter(|name| !name.is_empty()).collect();
+    names.sort_unstable();
+    names.dedup();
+    let name_bytes: Vec<Vec<u8>> = names.iter().map(|name| name.as_bytes().to_vec()).collect();
+    let parameters = phf_search::search(&name_bytes).expect("no perfect hash function found");
+
+    let mut generated = String::new();
+    writeln!(
+        generated,
+        "pub const STATION_NAMES_OFFSET: usize = {};",
+        parameters.offset
+    )
+    .unwrap();
+    writeln!(
+        generated,
+        "pub const STATION_NAMES_WIDTH: usize = {};",
+        parameters.width
+    )
+    .unwrap();
+    writeln!(
+        generated,
+        "pub const STATION_NAMES_HASH: PhfHash = {};",
+        parameters.hash
+    )
+    .unwrap();
+    writeln!(
+        generated,
+        "pub const STATION_NAMES_SIZE: usize = {};",
+        parameters.size
+    )
+    .unwrap();
+    writeln!(generated).unwrap();
+    writeln!(
+        generated,
+        "pub static STATION_NAMES: [&[u8]; {}] = [",
+        names.len()
+    )
+    .unwrap();
+    for name in names {
+        writeln!(generated, "    {name:?}.as_bytes(),").unwrap();
+    }
+    writeln!(generated, "];").unwrap();
+    let out_dir = env::var_os("OUT_DIR").unwrap();
+    fs::write(Path::new(&out_dir).join("station_names.rs"), generated).unwrap();
+}
@@ -0,0 +1,126 @@
+#ifndef BRC_H
+#define BRC_H
+
+/* Generated from src/capi.rs by cbindgen, do not edit. Regenerate it with
+ * `BRC_UPDATE_HEADER=1 cargo test --test capi`. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/**
+ * What a call returned, with a message from `brc_last_error` unless it is `BRC_STATUS_OK`.
+ */
+typedef enum BrcStatus {
+  BRC_STATUS_OK = 0,
+  /**
+   * A null pointer where one is not allowed.
+   */
+  BRC_STATUS_INVALID_ARGUMENT = 1,
+  /**
+   * The file could not be read.
+   */
+  BRC_STATUS_IO_ERROR = 2,
+  /**
+   * Aggregating failed unexpectedly. Only if the library is built with unwinding panics, like
+   * the `capi` profile, as in the `max` profile a failure aborts the whole process.
+   */
+  BRC_STATUS_FAILED = 3,
+} BrcStatus;
+
+/**
+ * The results of `brc_aggregate_file`, one row per station in name order.
+ */
+typedef struct BrcResults BrcResults;
+
+/**
+ * Options of `brc_aggregate_file`, zeroed for the defaults.
+ */
+typedef struct BrcOptions {
+  /**
+   * How many threads aggregate the file, or 0 for one per CPU.
+   */
+  uint32_t threads;
+} BrcOptions;
+
+/**
+ * The statistics of a station.
+ */
+typedef struct BrcRow {
+  /**
+   * The bytes of the name as they are in the file, null terminated, valid until the results
+   * are freed. They are not validated, so they may not be UTF-8 and may contain null bytes.
+   */
+  const char *name;
+  /**
+   * The length of the name in bytes, without the null terminator.
+   */
+  size_t name_len;
+  double min;
+  /**
+   * Rounded half up to one decimal, as the program writes it.
+   */
+  double mean;
+  double max;
+  uint64_t count;
+} BrcRow;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * Aggregates the `station;measurement` lines of the file at `path` with the pipeline of the
+ * program, and stores the results in `*out`, to be freed with `brc_results_free`. A last line
+ * without a line break is ignored.
+ *
+ * The library must be built with unwinding panics, e.g. `cargo build --profile capi`, for a
+ * failure to be returned as `BRC_STATUS_FAILED`: the `max` profile aborts on a panic, and so
+ * ends the program that loaded it.
+ *
+ * # Safety
+ * `path` must be a null terminated string, `opts` null for the defaults or valid, and `out`
+ * valid for writes.
+ */
+enum BrcStatus brc_aggregate_file(const char *path,
+                                  const struct BrcOptions *opts,
+                                  struct BrcResults **out);
+
+/**
+ * The number of rows.
+ *
+ * # Safety
+ * `results` must come from `brc_aggregate_file` and not be freed.
+ */
+size_t brc_results_len(const struct BrcResults *results);
+
+/**
+ * Copies the row at `index` to `*row` and returns true, or returns false if there is no such
+ * row, so the rows can be iterated until it returns false.
+ *
+ * # Safety
+ * `results` must come from `brc_aggregate_file` and not be freed, and `row` must be valid for
+ * writes.
+ */
+bool brc_results_row(const struct BrcResults *results, size_t index, struct BrcRow *row);
+
+/**
+ * Frees results and the names of their rows. Does nothing for null.
+ *
+ * # Safety
+ * `results` must be null, or come from `brc_aggregate_file` and not be freed yet.
+ */
+void brc_results_free(struct BrcResults *results);
+
+/**
+ * The message of the last failed call on this thread, valid until the next one fails.
+ */
+const char *brc_last_error(void);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* BRC_H */
@@ -0,0 +1,74 @@
+use std::{
+    io::{self, Write},
+    sync::Arc,
+};
+
+use arrow_array::{ArrayRef, Float64Array, RecordBatch, StringArray, UInt64Array};
+use arrow_ipc::writer::FileWriter;
+use arrow_schema::{DataType, Field, Schema};
+use parquet::arrow::ArrowWriter;
+
+use crate::{
+    my_hashmap::{Aggregator, Unit, Value},
+    output::Summary,
+};
+
+/// The rows of the CSV format as a single record batch: a string column for every key level,
+/// null below the level of the row, then a `Float64` column for every statistic in tenths and a
+/// `UInt64` column for every count.
+fn record_batch<E: Aggregator>(summary: &Summary<E>) -> RecordBatch {
+    let rows = summary.flat_rows();
+    let mut fields = Vec::new();
+    let mut columns: Vec<ArrayRef> = Vec::new();
+    for (level, &name) in summary.levels.iter().enumerate() {
+        fields.push(Field::new(name, DataType::Utf8, level > 0));
+        let keys = rows.iter().map(|(keys, _)| keys.get(level).copied());
+        columns.push(Arc::new(keys.collect::<StringArray>()));
+    }
+    let values: Vec<Vec<Value>> = rows
+        .iter()
+        .map(|(_, entry)| entry.fields(summary.options))
+        .collect();
+    for (i, (name, unit)) in E::field_names(summary.options).into_iter().enumerate() {
+        let column = values.iter().map(|fields| fields[i]);
+        match unit {
+            Unit::Tenths | Unit::Decimal => {
+                fields.push(Field::new(name, DataType::Float64, false));
+                let column = column.map(|value| match value {
+                    Value::Tenths(tenths) => tenths.0 as f64 / 10.0,
+                    Value::Decimal(decimal) => decimal.value,
+                    Value::Count(_) => unreachable!("count in a decimal column"),
+                });
+                columns.push(Arc::new(column.collect::<Float64Array>()));
+            }
+            Unit::Count => {
+                fields.push(Field::new(name, DataType::UInt64, false));
+                let column = column.map(|value| match value {
+                    Value::Count(count) => count,
+                    _ => unreachable!("decimal in a count column"),
+                });
+                columns.push(Arc::new(column.collect::<UInt64Array>()));
+            }
+        }
+    }
+    RecordBatch::try_new(Arc::new(Schema::new(fields)), columns).unwrap()
+}
+
+/// `--format arrow`, an Arrow IPC file (not a stream), so it can be memory mapped by readers.
+pub fn write_ipc<E: Aggregator>(summary: &Summary<E>, out: &mut impl Write) -> io::Result<()> {
+    let batch = record_batch(summary);
+    let mut writer = FileWriter::try_new(out, &batch.schema()).map_err(io::Error::other)?;
+    writer.write(&batch).map_err(io::Error::other)?;
+    writer.finish().map_err(io::Error::other)
+}
+
+/// `--format parquet`, with the default writer properties.
+pub fn write_parquet<E: Aggregator>(summary: &Summary<E>, out: &mut impl Write) -> io::Result<()> {
+    let batch = record_batch(summary);
+    // the writer needs a `Send` destination, which a locked stdout is not
+    let mut buffer = Vec::new();
+    let mut writer = ArrowWriter::try_new(&mut buffer, batch.schema(), None)?;
+    writer.write(&batch)?;
+    writer.close()?;
+    out.write_all(&buffer)
+}
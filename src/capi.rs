@@ -0,0 +1,175 @@
+use std::{
+    cell::RefCell,
+    ffi::{CStr, CString, OsStr, c_char},
+    io,
+    os::unix::ffi::OsStrExt,
+    panic,
+    path::Path,
+};
+
+use crate::use_phf;
+
+thread_local! {
+    static LAST_ERROR: RefCell<CString> = RefCell::new(CString::default());
+}
+
+/// What a call returned, with a message from `brc_last_error` unless it is `BRC_STATUS_OK`.
+#[repr(C)]
+pub enum BrcStatus {
+    Ok = 0,
+    /// A null pointer where one is not allowed.
+    InvalidArgument = 1,
+    /// The file could not be read.
+    IoError = 2,
+    /// Aggregating failed unexpectedly. Only if the library is built with unwinding panics, like
+    /// the `capi` profile, as in the `max` profile a failure aborts the whole process.
+    Failed = 3,
+}
+
+/// Options of `brc_aggregate_file`, zeroed for the defaults.
+#[repr(C)]
+pub struct BrcOptions {
+    /// How many threads aggregate the file, or 0 for one per CPU.
+    pub threads: u32,
+}
+
+/// The statistics of a station.
+#[derive(Clone, Copy)]
+#[repr(C)]
+pub struct BrcRow {
+    /// The bytes of the name as they are in the file, null terminated, valid until the results
+    /// are freed. They are not validated, so they may not be UTF-8 and may contain null bytes.
+    pub name: *const c_char,
+    /// The length of the name in bytes, without the null terminator.
+    pub name_len: usize,
+    pub min: f64,
+    /// Rounded half up to one decimal, as the program writes it.
+    pub mean: f64,
+    pub max: f64,
+    pub count: u64,
+}
+
+/// The results of `brc_aggregate_file`, one row per station in name order.
+pub struct BrcResults {
+    rows: Vec<BrcRow>,
+    // null terminated, pointed to by the rows
+    _names: Vec<Box<[u8]>>,
+}
+
+fn fail(status: BrcStatus, message: impl ToString) -> BrcStatus {
+    let message = CString::new(message.to_string().replace('\0', "")).unwrap();
+    LAST_ERROR.with(|last_error| *last_error.borrow_mut() = message);
+    status
+}
+
+fn aggregate_file(path: &Path, threads: u32) -> io::Result<BrcResults> {
+    let results = use_phf::aggregate_file(path, threads as usize)?;
+    let names: Vec<Box<[u8]>> = results
+        .iter()
+        .map(|(name, _)| [name, &b"\0"[..]].concat().into_boxed_slice())
+        .collect();
+    let rows = results
+        .iter()
+        .zip(&names)
+        .map(|((name, entry), stored_name)| {
+            let (min, mean, max) = entry.get_result();
+            BrcRow {
+                name: stored_name.as_ptr() as *const c_char,
+                name_len: name.len(),
+                min: min.0 as f64 / 10.0,
+                mean: mean.0 as f64 / 10.0,
+                max: max.0 as f64 / 10.0,
+                count: entry.count,
+            }
+        })
+        .collect();
+    Ok(BrcResults {
+        rows,
+        _names: names,
+    })
+}
+
+/// Aggregates the `station;measurement` lines of the file at `path` with the pipeline of the
+/// program, and stores the results in `*out`, to be freed with `brc_results_free`. A last line
+/// without a line break is ignored.
+///
+/// The library must be built with unwinding panics, e.g. `cargo build --profile capi`, for a
+/// failure to be returned as `BRC_STATUS_FAILED`: the `max` profile aborts on a panic, and so
+/// ends the program that loaded it.
+///
+/// # Safety
+/// `path` must be a null terminated string, `opts` null for the defaults or valid, and `out`
+/// valid for writes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn brc_aggregate_file(
+    path: *const c_char,
+    opts: *const BrcOptions,
+    out: *mut *mut BrcResults,
+) -> BrcStatus {
+    if path.is_null() || out.is_null() {
+        return fail(BrcStatus::InvalidArgument, "path and out must not be null");
+    }
+    let path = Path::new(OsStr::from_bytes(
+        unsafe { CStr::from_ptr(path) }.to_bytes(),
+    ));
+    let threads = unsafe { opts.as_ref() }.map_or(0, |opts| opts.threads);
+    // panics must not unwind into C
+    match panic::catch_unwind(|| aggregate_file(path, threads)) {
+        Ok(Ok(results)) => {
+            unsafe { *out = Box::into_raw(Box::new(results)) };
+            BrcStatus::Ok
+        }
+        Ok(Err(error)) => fail(BrcStatus::IoError, format!("{}: {error}", path.display())),
+        Err(panic) => {
+            let message = (panic.downcast_ref::<&str>().copied())
+                .or(panic.downcast_ref::<String>().map(String::as_str))
+                .unwrap_or("aggregating failed");
+            fail(BrcStatus::Failed, format!("{}: {message}", path.display()))
+        }
+    }
+}
+
+/// The number of rows.
+///
+/// # Safety
+/// `results` must come from `brc_aggregate_file` and not be freed.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn brc_results_len(results: *const BrcResults) -> usize {
+    unsafe { &*results }.rows.len()
+}
+
+/// Copies the row at `index` to `*row` and returns true, or returns false if there is no such
+/// row, so the rows can be iterated until it returns false.
+///
+/// # Safety
+/// `results` must come from `brc_aggregate_file` and not be freed, and `row` must be valid for
+/// writes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn brc_results_row(
+    results: *const BrcResults,
+    index: usize,
+    row: *mut BrcRow,
+) -> bool {
+    let Some(result) = unsafe { &*results }.rows.get(index) else {
+        return false;
+    };
+    unsafe { *row = *result };
+    true
+}
+
+/// Frees results and the names of their rows. Does nothing for null.
+///
+/// # Safety
+/// `results` must be null, or come from `brc_aggregate_file` and not be freed yet.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn brc_results_free(results: *mut BrcResults) {
+    if !results.is_null() {
+        drop(unsafe { Box::from_raw(results) });
+    }
+}
+
+/// The message of the last failed call on this thread, valid until the next one fails.
+#[unsafe(no_mangle)]
+pub extern "C" fn brc_last_error() -> *const c_char {
+    LAST_ERROR.with(|last_error| last_error.borrow().as_ptr())
+}
@@ -0,0 +1,120 @@
+use std::{
+    fs::File,
+    io::{self, Write},
+    os::unix::fs::FileExt,
+};
+
+use memchr::memrchr;
+
+use crate::{
+    filter::Selection,
+    my_hashmap::Aggregator,
+    partial::{self, Input, Layout, checksum},
+};
+
+const MAGIC: &[u8; 8] = b"1BRCCKPT";
+// 3 since the selection holds the bounds in tenths
+const VERSION: u32 = 3;
+
+/// How much of an append-only measurements file is aggregated: everything before `offset`, whose
+/// last line is identified by its length and hash so a replaced or truncated file is noticed.
+#[derive(Clone, Copy, PartialEq)]
+pub struct Position {
+    pub offset: u64,
+    pub last_line_len: u32,
+    pub last_line_hash: u64,
+}
+
+impl Position {
+    pub const START: Position = Position {
+        offset: 0,
+        last_line_len: 0,
+        last_line_hash: 0,
+    };
+
+    /// The position after `lines`, which start at this position and end with a line break.
+    pub fn after(self, lines: &[u8]) -> Position {
+        let Some((_, previous_lines)) = lines.split_last() else {
+            return self;
+        };
+        let last_line = &lines[memrchr(b'\n', previous_lines).map_or(0, |end| end + 1)..];
+        Position {
+            offset: self.offset + lines.len() as u64,
+            last_line_len: last_line.len() as u32,
+            last_line_hash: checksum(last_line),
+        }
+    }
+
+    /// Whether `file` still has the last line of this position where it was.
+    pub fn matches(&self, file: &File) -> io::Result<bool> {
+        if file.metadata()?.len() < self.offset {
+            return Ok(false);
+        }
+        let mut last_line = vec![0; self.last_line_len as usize];
+        let line_start = self.offset - self.last_line_len as u64;
+        file.read_exact_at(&mut last_line, line_start)?;
+        Ok(self.last_line_len == 0 || checksum(&last_line) == self.last_line_hash)
+    }
+}
+
+/// A checkpoint read by `read`.
+pub struct Checkpoint<'a> {
+    pub position: Position,
+    /// The `--include`, `--exclude`, `--min-value` and `--max-value` of the aggregated lines.
+    pub selection: Selection,
+    pub layout: Layout,
+    /// The entries of the partial summary, for `partial::read_entries`.
+    pub entries: Input<'a>,
+}
+
+/// Writes a checkpoint: a versioned header with the position and the selection, the partial
+/// summary of everything before it, and a checksum of it all.
+pub fn write<E: Aggregator>(
+    out: &mut dyn Write,
+    position: Position,
+    layout: &Layout,
+    selection: &Selection,
+    results: &[(&[u8], &E)],
+) -> io::Result<()> {
+    let mut bytes = MAGIC.to_vec();
+    bytes.extend_from_slice(&VERSION.to_le_bytes());
+    bytes.extend_from_slice(&position.offset.to_le_bytes());
+    bytes.extend_from_slice(&position.last_line_len.to_le_bytes());
+    bytes.extend_from_slice(&position.last_line_hash.to_le_bytes());
+    selection.serialize(&mut bytes);
+    partial::write(&mut bytes, layout, results)?;
+    bytes.extend_from_slice(&checksum(&bytes).to_le_bytes());
+    out.write_all(&bytes)
+}
+
+/// Checks the header and checksum of a checkpoint, and reads its position, selection and
+/// layout.
+pub fn read(bytes: &[u8]) -> Result<Checkpoint<'_>, String> {
+    if !bytes.starts_with(MAGIC) {
+        return Err("not a checkpoint".to_string());
+    }
+    let Some((contents, expected)) = bytes.split_last_chunk::<8>() else {
+        return Err("truncated checkpoint".to_string());
+    };
+    if checksum(contents) != u64::from_le_bytes(*expected) {
+        return Err("corrupt checkpoint, the checksum does not match".to_string());
+    }
+    let mut input = Input::new(&contents[MAGIC.len()..]);
+    let version = u32::from_le_bytes(input.array()?);
+    if version != VERSION {
+        return Err(format!("unsupported checkpoint version {version}"));
+    }
+    let position = Position {
+        offset: u64::from_le_bytes(input.array()?),
+        last_line_len: u32::from_le_bytes(input.array()?),
+        last_line_hash: u64::from_le_bytes(input.array()?),
+    };
+    let selection = Selection::deserialize(&mut input)?;
+    let (layout, entries) = partial::read(input.rest())?;
+    Ok(Checkpoint {
+        position,
+        selection,
+        layout,
+        entries,
+    })
+}
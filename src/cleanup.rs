@@ -12,10 +12,10 @@ use rustc_hash::FxHashMap;
 use memchr::memchr;
 
 struct StationEntry {
-    sum: i32,
+    sum: i64,
     min: i32,
     max: i32,
-    count: i32,
+    count: u64,
 }
 
 #[derive(Eq)]
@@ -165,13 +165,13 @@ pub fn run(mut writer: PipeWriter) {
                 if measurement > e.max {
                     e.max = measurement;
                 }
-                e.sum += measurement;
+                e.sum += measurement as i64;
                 e.count += 1;
             })
             .or_insert(StationEntry {
                 min: measurement,
                 max: measurement,
-                sum: measurement,
+                sum: measurement as i64,
                 count: 1,
             });
     }
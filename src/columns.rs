@@ -0,0 +1,88 @@
+use std::{
+    cmp::Ordering,
+    io::{self, Write},
+};
+
+use crate::{
+    my_hashmap::{Aggregator, Count, Unit, Value},
+    partial::Input,
+    ranking::Metric,
+};
+
+/// `--columns N`, for `station;value;value;...` lines: one aggregator per numeric column,
+/// printed in column order and separated by `;`.
+#[derive(Clone)]
+pub struct Columns<E, const N: usize>([E; N]);
+
+impl<E: Aggregator, const N: usize> Aggregator for Columns<E, N> {
+    type Options = E::Options;
+    type Measurement = [E::Measurement; N];
+
+    fn empty(options: &E::Options) -> Columns<E, N> {
+        Columns(std::array::from_fn(|_| E::empty(options)))
+    }
+
+    fn add_measurement(&mut self, measurements: [E::Measurement; N]) {
+        for (entry, measurement) in self.0.iter_mut().zip(measurements) {
+            entry.add_measurement(measurement);
+        }
+    }
+
+    fn merge(&mut self, other: &Columns<E, N>) {
+        for (entry, other_entry) in self.0.iter_mut().zip(&other.0) {
+            entry.merge(other_entry);
+        }
+    }
+
+    fn count(&self) -> Count {
+        self.0[0].count()
+    }
+
+    /// By the first column.
+    fn compare(&self, other: &Columns<E, N>, metric: Metric) -> Ordering {
+        self.0[0].compare(&other.0[0], metric)
+    }
+
+    fn write_result(&self, out: &mut impl Write, options: &E::Options) -> io::Result<()> {
+        let mut separator = "";
+        for entry in &self.0 {
+            out.write_all(separator.as_bytes())?;
+            entry.write_result(out, options)?;
+            separator = ";";
+        }
+        Ok(())
+    }
+
+    /// The names of every column's fields, numbered from 1, e.g. `min_1`, ..., `count_2`.
+    fn field_names(options: &E::Options) -> Vec<(String, Unit)> {
+        let names = E::field_names(options);
+        (1..=N)
+            .flat_map(|column| {
+                let names = names.iter();
+                names.map(move |(name, unit)| (format!("{name}_{column}"), *unit))
+            })
+            .collect()
+    }
+
+    fn serialize(&self, out: &mut Vec<u8>) {
+        for entry in &self.0 {
+            entry.serialize(out);
+        }
+    }
+
+    fn deserialize(input: &mut Input, options: &E::Options) -> Result<Columns<E, N>, String> {
+        let entries: Vec<E> = (0..N)
+            .map(|_| E::deserialize(input, options))
+            .collect::<Result<_, _>>()?;
+        Ok(Columns(
+            entries.try_into().unwrap_or_else(|_| unreachable!()),
+        ))
+    }
+
+    fn fields(&self, options: &E::Options) -> Vec<Value> {
+        self.0
+            .iter()
+            .flat_map(|entry| entry.fields(options))
+            .collect()
+    }
+}
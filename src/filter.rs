@@ -0,0 +1,193 @@
+use std::ops::RangeInclusive;
+
+use rustc_hash::FxHashSet;
+
+use crate::{my_phf::Phf, partial::Input};
+
+/// What `--include`, `--exclude`, `--min-value` and `--max-value` select, with the names sorted
+/// so the same selection compares equal however it was given. Kept in checkpoints, which are
+/// only resumed with the same selection.
+#[derive(Clone, PartialEq)]
+pub struct Selection {
+    /// `None` to include every station that is not excluded.
+    pub include: Option<Vec<Vec<u8>>>,
+    pub exclude: Vec<Vec<u8>>,
+    pub values: RangeInclusive<f64>,
+    /// `values` in tenths, rounded inwards, for measurements in tenths.
+    pub tenths: RangeInclusive<i32>,
+}
+
+impl Default for Selection {
+    fn default() -> Selection {
+        Selection {
+            include: None,
+            exclude: Vec::new(),
+            values: f64::NEG_INFINITY..=f64::INFINITY,
+            tenths: i32::MIN..=i32::MAX,
+        }
+    }
+}
+
+impl Selection {
+    pub fn new(
+        include: Option<Vec<Vec<u8>>>,
+        exclude: Vec<Vec<u8>>,
+        values: RangeInclusive<f64>,
+        tenths: RangeInclusive<i32>,
+    ) -> Selection {
+        let normalize = |mut names: Vec<Vec<u8>>| {
+            names.sort_unstable();
+            names.dedup();
+            names
+        };
+        Selection {
+            include: include.map(normalize),
+            exclude: normalize(exclude),
+            values,
+            tenths,
+        }
+    }
+
+    /// Whether every line is counted, so no filter is needed.
+    pub fn is_everything(&self) -> bool {
+        *self == Selection::default()
+    }
+
+    pub fn serialize(&self, bytes: &mut Vec<u8>) {
+        let write_names = |bytes: &mut Vec<u8>, names: &[Vec<u8>]| {
+            bytes.extend_from_slice(&(names.len() as u32).to_le_bytes());
+            for name in names {
+                bytes.extend_from_slice(&(name.len() as u16).to_le_bytes());
+                bytes.extend_from_slice(name);
+            }
+        };
+        bytes.push(self.include.is_some() as u8);
+        if let Some(include) = &self.include {
+            write_names(bytes, include);
+        }
+        write_names(bytes, &self.exclude);
+        bytes.extend_from_slice(&self.values.start().to_le_bytes());
+        bytes.extend_from_slice(&self.values.end().to_le_bytes());
+        bytes.extend_from_slice(&self.tenths.start().to_le_bytes());
+        bytes.extend_from_slice(&self.tenths.end().to_le_bytes());
+    }
+
+    pub fn deserialize(input: &mut Input) -> Result<Selection, String> {
+        let read_names = |input: &mut Input| {
+            (0..u32::from_le_bytes(input.array()?))
+                .map(|_| {
+                    let len = u16::from_le_bytes(input.array()?);
+                    Ok(input.bytes(len as usize)?.to_vec())
+                })
+                .collect::<Result<Vec<_>, String>>()
+        };
+        let [has_include] = input.array()?;
+        let include = match has_include {
+            0 => None,
+            _ => Some(read_names(input)?),
+        };
+        let exclude = read_names(input)?;
+        let start = f64::from_le_bytes(input.array()?);
+        let end = f64::from_le_bytes(input.array()?);
+        let tenths_start = i32::from_le_bytes(input.array()?);
+        let tenths_end = i32::from_le_bytes(input.array()?);
+        Ok(Selection {
+            include,
+            exclude,
+            values: start..=end,
+            tenths: tenths_start..=tenths_end,
+        })
+    }
+}
+
+/// `--include`, `--exclude`, `--min-value` and `--max-value`, checked for every line.
+pub struct Filter {
+    // bit `i` is set if the station in PHF slot `i` is counted, so known stations cost a bit test
+    slots: Box<[u64]>,
+    // the same decision for names that are not part of the PHF, only used for overflow names
+    include: Option<FxHashSet<Vec<u8>>>,
+    exclude: FxHashSet<Vec<u8>>,
+    values: RangeInclusive<f64>,
+    // `values` rounded inwards to tenths, for measurements in tenths
+    tenths: RangeInclusive<i32>,
+}
+
+impl Filter {
+    /// With no `include` list every station is counted, unless it is excluded.
+    pub fn new(phf: &Phf, selection: &Selection) -> Filter {
+        let mut filter = Filter {
+            slots: vec![0; phf.size().div_ceil(64)].into_boxed_slice(),
+            include: (selection.include.as_ref()).map(|names| names.iter().cloned().collect()),
+            exclude: selection.exclude.iter().cloned().collect(),
+            values: selection.values.clone(),
+            tenths: selection.tenths.clone(),
+        };
+        for (name, index) in phf.names() {
+            if filter.accepts_name(name) {
+                filter.slots[index / 64] |= 1 << (index % 64);
+            }
+        }
+        filter
+    }
+
+    pub fn accepts_slot(&self, index: usize) -> bool {
+        let bits = unsafe { *self.slots.get_unchecked(index / 64) };
+        bits & (1 << (index % 64)) != 0
+    }
+
+    /// The name must be followed by at least 32 readable bytes, see `Phf::is_name_at`.
+    pub fn accepts_station(&self, phf: &Phf, name: &[u8]) -> bool {
+        let index = phf.get_name_index(name);
+        if phf.is_name_at(index, name) {
+            self.accepts_slot(index)
+        } else {
+            self.accepts_name(name)
+        }
+    }
+
+    #[cold]
+    pub fn accepts_name(&self, name: &[u8]) -> bool {
+        self.include
+            .as_ref()
+            .is_none_or(|include| include.contains(name))
+            && !self.exclude.contains(name)
+    }
+
+    /// Whether a measurement in tenths is within `--min-value` and `--max-value`.
+    pub fn accepts_tenths(&self, tenths: i32) -> bool {
+        self.tenths.contains(&tenths)
+    }
+
+    /// Whether a `Decimal` measurement is within `--min-value` and `--max-value`.
+    pub fn accepts_value(&self, value: f64) -> bool {
+        self.values.contains(&value)
+    }
+}
+
+/// `--min-value` or `--max-value`, a decimal value like `-12.34`, along with the bound in tenths,
+/// rounded up for a minimum and down for a maximum so no measurement beyond it is accepted. The
+/// tenths are taken from the digits, as the value may not be exact.
+pub fn parse_bound(text: &str, is_min: bool) -> (f64, i32) {
+    let value = text.parse().expect("invalid value");
+    let (negative, digits) = match text.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, text.strip_prefix('+').unwrap_or(text)),
+    };
+    let (whole, fraction) = digits.split_once('.').unwrap_or((digits, ""));
+    assert!(
+        (whole.bytes().chain(fraction.bytes())).all(|c| c.is_ascii_digit()),
+        "invalid value: {text}"
+    );
+    let tenth = fraction.bytes().next().unwrap_or(b'0');
+    // saturating, as a bound beyond every measurement is as good as any other
+    let magnitude = (whole.bytes().chain([tenth])).fold(0i64, |magnitude, digit| {
+        magnitude
+            .saturating_mul(10)
+            .saturating_add((digit - b'0') as i64)
+    });
+    let between_tenths = fraction.bytes().skip(1).any(|digit| digit != b'0');
+    // away from zero is up for a positive minimum and down for a negative maximum
+    let magnitude = magnitude + (between_tenths && is_min != negative) as i64;
+    let tenths = if negative { -magnitude } else { magnitude };
+    (value, tenths.clamp(i32::MIN as i64, i32::MAX as i64) as i32)
+}
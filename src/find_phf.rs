@@ -1,98 +1,97 @@
-use std::{
-    arch::x86_64::_pext_u64,
-    hash::{Hash, Hasher},
-};
+use std::{ops::Range, time::Instant};
 
-use rayon::iter::{IntoParallelIterator, ParallelIterator};
-use rustc_hash::FxHasher;
+use crate::{
+    my_phf::read_names_file,
+    phf_search::{PhfHash, PhfParameters, SearchSpace, search_in},
+};
 
-use crate::station_names::STATION_NAMES;
+const DEFAULT_SEEDS: usize = 1024;
+// keep the low 7, 6, 5 and 4 bits of every sampled byte
+const PEXT_MASKS: [u64; 4] = [
+    0x7f7f7f7f7f7f7f7f,
+    0x3f3f3f3f3f3f3f3f,
+    0x1f1f1f1f1f1f1f1f,
+    0x0f0f0f0f0f0f0f0f,
+];
 
-fn get_name_sample(name: &[u8]) -> usize {
-    const OFFSET: usize = 1;
-    let ptr = unsafe { name.as_ptr().add(OFFSET) } as *const u64;
-    let sample = unsafe { ptr.read_unaligned() };
-    let len = (name.len() - 1).min(8);
-    let to_mask = len * 8;
-    let mask = u64::MAX >> (64 - to_mask);
-    (sample & mask) as usize
-}
-
-pub fn print_phf() {
-    for name in STATION_NAMES {
-        let sample = get_name_sample(name);
-        println!("{}:{}", std::str::from_utf8(name).unwrap(), sample % 13779)
+/// Accepts `start..end`, `start..=end` or a single value.
+fn parse_range(arg: &str) -> Range<usize> {
+    let parse = |value: &str| value.parse::<usize>().expect("invalid range");
+    if let Some((start, end)) = arg.split_once("..=") {
+        parse(start)..parse(end) + 1
+    } else if let Some((start, end)) = arg.split_once("..") {
+        parse(start)..parse(end)
+    } else {
+        parse(arg)..parse(arg) + 1
     }
 }
 
-const THREADS: usize = 22;
-const MAX_DIVISOR: usize = 7089;
-
-pub fn find_seed_fxhash() {
-    for divisor in 413..MAX_DIVISOR {
-        (0..THREADS).into_par_iter().for_each(|tid| {
-            let mut vec = vec![false; MAX_DIVISOR];
-            for seed in (tid..10000).step_by(THREADS) {
-                let mut found = true;
-                for name in STATION_NAMES.iter() {
-                    let sample = get_name_sample(name);
-                    let mut hasher = FxHasher::with_seed(seed);
-                    sample.hash(&mut hasher);
-                    let hash = hasher.finish() as usize;
-                    let vec_index = hash % divisor;
-                    if !vec[vec_index] {
-                        vec[vec_index] = true;
-                    } else {
-                        vec[..divisor].fill(false);
-                        found = false;
-                        break;
-                    }
-                }
-                if found {
-                    println!("Seed Found: {seed} with divisor: {divisor}");
-                    unsafe { libc::exit(0) };
-                }
-            }
-        });
-        println!("Failed {divisor}");
-    }
-    println!("Failed");
+fn print_constants(parameters: &PhfParameters) {
+    println!(
+        "pub const STATION_NAMES_OFFSET: usize = {};",
+        parameters.offset
+    );
+    println!(
+        "pub const STATION_NAMES_WIDTH: usize = {};",
+        parameters.width
+    );
+    println!(
+        "pub const STATION_NAMES_HASH: PhfHash = {};",
+        parameters.hash
+    );
+    println!("pub const STATION_NAMES_SIZE: usize = {};", parameters.size);
 }
-pub fn find_seed() {
-    const OFFSET: usize = 1;
-    (0..THREADS).into_par_iter().for_each(|tid| {
-        let mut vec = vec![false; 1 << 20];
-        for divisor in (tid + 413..vec.len()).step_by(THREADS) {
-            let mut found = true;
-            for name in STATION_NAMES.iter() {
-                let ptr = unsafe { name.as_ptr().add(OFFSET) } as *const u64;
-                let sample = unsafe { ptr.read_unaligned() };
-                let len_mask = if name.len() > 8 + OFFSET {
-                    !0
-                } else {
-                    (1 << ((name.len() - OFFSET) * 8 - OFFSET)) - 1
-                };
-                let masked_sample = sample & len_mask;
-                // let masked_sample = unsafe {
-                //     _pext_u64(
-                //         masked_sample,
-                //         0b00011111_00011111_00011111_00011111_00011111_00011111_00011111_00011111,
-                //     )
-                // };
-                let vec_index = masked_sample as usize % divisor;
-                if !vec[vec_index] {
-                    vec[vec_index] = true;
-                } else {
-                    vec[..divisor].fill(false);
-                    found = false;
-                    break;
-                }
-            }
-            if found {
-                println!("Divisor Found: {divisor}");
-                unsafe { libc::exit(0) };
-            }
+
+/// `find-phf <names file> [--threads N] [--offsets RANGE] [--widths RANGE]
+/// [--hash modulo|fxhash|pext] [--seeds N] [--max-size N]`
+///
+/// Prints every improvement to stderr as it is found, and the smallest table as Rust constants,
+/// which `Phf` takes as its `PhfParameters`. `fxhash` tries the seeds `0..N`, and `pext` masks
+/// keeping the low 7, 6, 5 or 4 bits of every sampled byte.
+pub fn run(mut args: impl Iterator<Item = String>) {
+    let names = read_names_file(&args.next().expect("missing names file"));
+    let mut thread_count = std::thread::available_parallelism().map_or(1, |n| n.get());
+    let mut space = SearchSpace::default();
+    let mut family = "modulo".to_string();
+    let mut seeds = DEFAULT_SEEDS;
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .unwrap_or_else(|| panic!("missing value for {arg}"))
+        };
+        match arg.as_str() {
+            "--threads" => thread_count = value().parse().expect("invalid thread count"),
+            "--offsets" => space.offsets = parse_range(&value()),
+            "--widths" => space.widths = parse_range(&value()),
+            "--max-size" => space.max_size = value().parse().expect("invalid table size"),
+            "--hash" => family = value(),
+            "--seeds" => seeds = value().parse().expect("invalid seed count"),
+            _ => panic!("unknown argument: {arg}"),
         }
+    }
+    assert!(space.widths.end <= 9, "samples are at most 8 bytes wide");
+    let hashes: Vec<PhfHash> = match family.as_str() {
+        "modulo" => vec![PhfHash::Modulo],
+        "fxhash" => (0..seeds).map(|seed| PhfHash::FxHash { seed }).collect(),
+        "pext" => PEXT_MASKS.map(|mask| PhfHash::Pext { mask }).to_vec(),
+        _ => panic!("unknown hash family: {family}"),
+    };
+    rayon::ThreadPoolBuilder::new()
+        .num_threads(thread_count)
+        .build_global()
+        .unwrap();
+
+    let start = Instant::now();
+    let found = search_in(&names, &space, &hashes, |parameters: &PhfParameters| {
+        eprintln!(
+            "[{:>8.2?}] size {}: offset {}, width {}, {}",
+            start.elapsed(),
+            parameters.size,
+            parameters.offset,
+            parameters.width,
+            parameters.hash,
+        );
     });
-    println!("Failed");
+    print_constants(&found.unwrap_or_else(|error| panic!("{error}")));
+    eprintln!("[{:>8.2?}] search done", start.elapsed());
 }
@@ -0,0 +1,232 @@
+use std::{
+    collections::BTreeMap,
+    fs::{self, File},
+    io::{self, ErrorKind, Read, Seek, SeekFrom},
+    os::{
+        fd::{AsRawFd, FromRawFd},
+        unix::{ffi::OsStrExt, fs::MetadataExt},
+    },
+    path::Path,
+    time::{Duration, Instant},
+};
+
+use memchr::memrchr;
+
+use crate::{
+    checkpoint::Position,
+    my_hashmap::Aggregator,
+    my_phf::Phf,
+    use_phf::{Fields, MARGIN, Report, Task, aggregate_chunks, split_chunks},
+};
+
+const PATH: &str = "measurements.txt";
+
+/// `--follow`: after aggregating measurements.txt, keeps aggregating the lines appended to it,
+/// and writes a refreshed summary every `--interval` seconds and on SIGUSR1. Never returns.
+pub(crate) struct Follow<'a, I> {
+    /// The complete lines of the file when following started.
+    pub(crate) chunks: I,
+    pub(crate) file: File,
+    /// The end of `chunks`, where the lines after them start.
+    pub(crate) position: Position,
+    pub(crate) thread_count: usize,
+    pub(crate) interval: Option<Duration>,
+    pub(crate) phf: &'a Phf,
+    pub(crate) report: &'a Report,
+}
+
+impl<'a, I: Iterator<Item = &'a [u8]> + Send> Task for Follow<'a, I> {
+    fn run<E: Aggregator<Measurement: Fields>>(self, options: &E::Options) {
+        // watching first, so lines appended while the file is aggregated wake up the loop
+        let mask = libc::IN_MODIFY | libc::IN_CREATE | libc::IN_MOVED_TO;
+        let events =
+            watch_directory(Path::new("."), mask).expect("failed to watch measurements.txt");
+        let signals = signal_fd().expect("failed to receive SIGUSR1");
+        let filter = self.report.filter.as_ref();
+        let mut stations = Stations::default();
+        let map = aggregate_chunks::<E>(self.chunks, self.phf, options, filter);
+        stations.add(map.results());
+        self.report.write_update(stations.results(), options);
+        let mut tail = Tail {
+            file: self.file,
+            position: self.position,
+            pending: Vec::new(),
+        };
+        let mut next_update = self.interval.map(|interval| Instant::now() + interval);
+        loop {
+            let timeout = next_update.map_or(-1, |next_update| {
+                let timeout = next_update.saturating_duration_since(Instant::now());
+                timeout.as_millis().min(i32::MAX as u128) as i32
+            });
+            wait(&[&events, &signals], timeout).expect("poll failed");
+            drain(&events).expect("failed to read file events");
+            let requested = drain(&signals).expect("failed to read signals");
+
+            let mut lines = tail.read_lines().expect("failed to read measurements.txt");
+            if !lines.is_empty() {
+                lines.resize(lines.len() + MARGIN, 0);
+                let chunks = split_chunks(&lines, self.thread_count);
+                let map = aggregate_chunks::<E>(chunks, self.phf, options, filter);
+                stations.add(map.results());
+            }
+            if requested || next_update.is_some_and(|next_update| Instant::now() >= next_update) {
+                self.report.write_update(stations.results(), options);
+                next_update = self.interval.map(|interval| Instant::now() + interval);
+            }
+        }
+    }
+}
+
+/// The live results, with owned names, as the names of unknown stations point into lines that
+/// are dropped once aggregated.
+pub(crate) struct Stations<E>(BTreeMap<Box<[u8]>, E>);
+
+impl<E> Default for Stations<E> {
+    fn default() -> Stations<E> {
+        Stations(BTreeMap::new())
+    }
+}
+
+impl<E: Aggregator> Stations<E> {
+    pub(crate) fn add(&mut self, results: Vec<(&[u8], &E)>) {
+        for (name, entry) in results {
+            match self.0.get_mut(name) {
+                Some(station) => station.merge(entry),
+                None => _ = self.0.insert(name.into(), entry.clone()),
+            }
+        }
+    }
+
+    pub(crate) fn results(&self) -> Vec<(&[u8], &E)> {
+        self.0
+            .iter()
+            .map(|(name, entry)| (&**name, entry))
+            .collect()
+    }
+}
+
+/// The followed file, read from where the previous read stopped.
+struct Tail {
+    file: File,
+    /// The end of the complete lines that were read.
+    position: Position,
+    /// The start of a line that is still being appended.
+    pending: Vec<u8>,
+}
+
+impl Tail {
+    /// The complete lines appended since the last call. Follows measurements.txt when it is
+    /// replaced by a new file, after reading the rest of the old one, and reads a file that was
+    /// truncated from its start again.
+    fn read_lines(&mut self) -> io::Result<Vec<u8>> {
+        let mut lines = Vec::new();
+        self.read_appended(&mut lines)?;
+        if let Ok(metadata) = fs::metadata(PATH) {
+            let current = self.file.metadata()?;
+            if (metadata.dev(), metadata.ino()) != (current.dev(), current.ino()) {
+                // the incomplete last line of the old file is never completed
+                self.file = File::open(PATH)?;
+                self.position = Position::START;
+                self.pending.clear();
+                self.read_appended(&mut lines)?;
+            }
+        }
+        Ok(lines)
+    }
+
+    fn read_appended(&mut self, lines: &mut Vec<u8>) -> io::Result<()> {
+        let read_end = self.position.offset + self.pending.len() as u64;
+        if self.file.metadata()?.len() < read_end || !self.position.matches(&self.file)? {
+            self.position = Position::START;
+            self.pending.clear();
+        }
+        let mut file = &self.file;
+        file.seek(SeekFrom::Start(
+            self.position.offset + self.pending.len() as u64,
+        ))?;
+        file.read_to_end(&mut self.pending)?;
+        if let Some(end) = memrchr(b'\n', &self.pending) {
+            let start = lines.len();
+            lines.extend(self.pending.drain(..end + 1));
+            self.position = self.position.after(&lines[start..]);
+        }
+        Ok(())
+    }
+}
+
+/// Blocks SIGUSR1, so it is only received through `signal_fd` instead of ending the process.
+/// Threads inherit the signal mask, so this has to be called before any are started.
+pub(crate) fn block_signals() {
+    let set = sigusr1();
+    let result = unsafe { libc::pthread_sigmask(libc::SIG_BLOCK, &set, std::ptr::null_mut()) };
+    assert!(result == 0, "failed to block SIGUSR1");
+}
+
+fn sigusr1() -> libc::sigset_t {
+    let mut set = unsafe { std::mem::zeroed() };
+    unsafe {
+        libc::sigemptyset(&mut set);
+        libc::sigaddset(&mut set, libc::SIGUSR1);
+    }
+    set
+}
+
+fn signal_fd() -> io::Result<File> {
+    let fd = unsafe { libc::signalfd(-1, &sigusr1(), libc::SFD_NONBLOCK | libc::SFD_CLOEXEC) };
+    if fd < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(unsafe { File::from_raw_fd(fd) })
+}
+
+/// Non-blocking inotify events for a directory. Watching a directory includes changes to the
+/// files in it and files being created or moved into it, as when measurements.txt is rotated.
+pub(crate) fn watch_directory(path: &Path, mask: u32) -> io::Result<File> {
+    let fd = unsafe { libc::inotify_init1(libc::IN_NONBLOCK | libc::IN_CLOEXEC) };
+    if fd < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    let file = unsafe { File::from_raw_fd(fd) };
+    let mut path = path.as_os_str().as_bytes().to_vec();
+    path.push(0);
+    if unsafe { libc::inotify_add_watch(fd, path.as_ptr() as *const libc::c_char, mask) } < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(file)
+}
+
+/// Waits until one of `files` can be read, for at most `timeout` milliseconds unless it is -1,
+/// or until a signal interrupts the wait.
+pub(crate) fn wait(files: &[&File], timeout: libc::c_int) -> io::Result<()> {
+    let mut fds: Vec<libc::pollfd> = files
+        .iter()
+        .map(|file| libc::pollfd {
+            fd: file.as_raw_fd(),
+            events: libc::POLLIN,
+            revents: 0,
+        })
+        .collect();
+    if unsafe { libc::poll(fds.as_mut_ptr(), fds.len() as libc::nfds_t, timeout) } < 0 {
+        let error = io::Error::last_os_error();
+        if error.kind() != ErrorKind::Interrupted {
+            return Err(error);
+        }
+    }
+    Ok(())
+}
+
+/// Reads everything that is ready from a non-blocking file, and returns whether there was any.
+/// The events themselves do not matter, the file is checked after every wake up.
+fn drain(mut file: &File) -> io::Result<bool> {
+    let mut buffer = [0; 4096];
+    let mut any = false;
+    loop {
+        match file.read(&mut buffer) {
+            Ok(0) => return Ok(any),
+            Ok(_) => any = true,
+            Err(error) if error.kind() == ErrorKind::WouldBlock => return Ok(any),
+            Err(error) if error.kind() == ErrorKind::Interrupted => {}
+            Err(error) => return Err(error),
+        }
+    }
+}
@@ -1,29 +0,0 @@
-pub fn hash(name: &[u8], len: usize) -> usize {
-    const ASSO_VALUES: [u16; 256] = [
-        1269, 1269, 1269, 1269, 1269, 1269, 1269, 1269, 1269, 1269, 1269, 1269, 1269, 1269, 1269,
-        1269, 1269, 1269, 1269, 1269, 1269, 1269, 1269, 1269, 1269, 1269, 1269, 1269, 1269, 1269,
-        1269, 1269, 110, 1269, 1269, 1269, 1269, 1269, 1269, 0, 1269, 0, 1269, 1269, 1269, 0, 0,
-        1269, 1269, 1269, 1269, 1269, 1269, 1269, 1269, 1269, 1269, 1269, 1269, 1269, 1269, 1269,
-        1269, 1269, 1269, 145, 10, 240, 65, 175, 33, 413, 320, 280, 30, 190, 325, 0, 340, 335, 170,
-        1269, 385, 105, 115, 40, 456, 190, 0, 340, 0, 1269, 1269, 1269, 1269, 1269, 1269, 0, 280,
-        280, 310, 15, 15, 250, 225, 5, 210, 35, 25, 90, 0, 30, 325, 5, 5, 20, 105, 200, 275, 315,
-        5, 260, 130, 1269, 1269, 1269, 1269, 1269, 1269, 1269, 1269, 1269, 1269, 1269, 1269, 1269,
-        1269, 1269, 1269, 1269, 1269, 1269, 1269, 1269, 1269, 1269, 1269, 1269, 1269, 1269, 1269,
-        1269, 1269, 0, 1269, 1269, 1269, 1269, 1269, 1269, 1269, 1269, 1269, 1269, 1269, 1269,
-        1269, 1269, 5, 325, 1269, 1269, 1269, 1269, 1269, 1269, 1269, 1269, 1269, 1269, 1269, 1269,
-        1269, 1269, 20, 1269, 1269, 1269, 10, 1269, 1269, 1269, 1269, 1269, 1269, 5, 10, 0, 1269,
-        1269, 1269, 1269, 1269, 1269, 1269, 1269, 1269, 1269, 1269, 1269, 1269, 1269, 1269, 1269,
-        1269, 1269, 1269, 1269, 1269, 1269, 1269, 1269, 1269, 1269, 1269, 1269, 1269, 1269, 1269,
-        1269, 1269, 1269, 1269, 1269, 1269, 1269, 1269, 1269, 1269, 1269, 1269, 1269, 1269, 1269,
-        1269, 1269, 1269, 1269, 1269, 1269, 1269, 1269, 1269, 1269, 1269, 1269,
-    ];
-    let mut hval =
-        len + ASSO_VALUES[name[0] as usize] as usize + ASSO_VALUES[name[len - 1] as usize] as usize;
-    if len > 2 {
-        hval += ASSO_VALUES[name[2] as usize] as usize;
-    }
-    if len > 4 {
-        hval += ASSO_VALUES[name[4] as usize] as usize;
-    }
-    hval
-}
@@ -0,0 +1,119 @@
+use std::{borrow::Cow, collections::BTreeMap};
+
+use regex::bytes::Regex;
+use rustc_hash::FxHashMap;
+
+use crate::{
+    my_hashmap::Aggregator,
+    output::{Row, Summary},
+    ranking::Ranking,
+};
+
+/// Station names and their statistics, sorted by name.
+type Results<'a, E> = Vec<(&'a [u8], &'a E)>;
+
+/// How a station name is turned into the name of its group.
+enum GroupKey {
+    /// The first N bytes, cut back to a character boundary.
+    Prefix(usize),
+    /// A `station;group` file, stations that are not in it are their own group.
+    Lookup(FxHashMap<Vec<u8>, Vec<u8>>),
+    /// The first capture group, or the whole match if there is none. Stations that do not
+    /// match are their own group.
+    Regex(Regex),
+}
+
+/// `--group-by prefix:N|file:PATH|regex:PATTERN [--drill-down]`, applied to the final results,
+/// so aggregation itself is unchanged.
+pub struct GroupBy {
+    key: GroupKey,
+    /// Also print every station of a group after its roll-up.
+    pub drill_down: bool,
+}
+
+impl GroupBy {
+    pub fn parse(arg: &str) -> GroupBy {
+        let (kind, value) = arg.split_once(':').expect("invalid group by");
+        let key = match kind {
+            "prefix" => GroupKey::Prefix(value.parse().expect("invalid prefix length")),
+            "file" => {
+                let lookup = std::fs::read(value)
+                    .expect("group file not found")
+                    .split(|&c| c == b'\n')
+                    .filter(|line| !line.is_empty())
+                    .map(|line| {
+                        let separator = line.iter().position(|&c| c == b';');
+                        let (station, group) = line.split_at(separator.expect("missing group"));
+                        let group = &group[1..];
+                        assert!(std::str::from_utf8(group).is_ok(), "group is not UTF-8");
+                        (station.to_vec(), group.to_vec())
+                    })
+                    .collect();
+                GroupKey::Lookup(lookup)
+            }
+            "regex" => GroupKey::Regex(Regex::new(value).expect("invalid regex")),
+            _ => panic!("unknown group by: {kind}"),
+        };
+        GroupBy {
+            key,
+            drill_down: false,
+        }
+    }
+
+    fn get_group<'a>(&'a self, name: &'a [u8]) -> &'a [u8] {
+        match &self.key {
+            GroupKey::Prefix(len) => {
+                let mut len = (*len).min(name.len());
+                // continuation bytes of a UTF-8 character look like 0b10xxxxxx
+                while len > 0 && len < name.len() && name[len] & 0xc0 == 0x80 {
+                    len -= 1;
+                }
+                &name[..len]
+            }
+            GroupKey::Lookup(lookup) => lookup.get(name).map_or(name, |group| group),
+            GroupKey::Regex(regex) => match regex.captures(name) {
+                Some(captures) => captures.get(1).or(captures.get(0)).unwrap().as_bytes(),
+                None => name,
+            },
+        }
+    }
+
+    /// Merges the results of every group, followed by the results of its stations when
+    /// drilling down. A ranking applies to the groups, their stations stay in name order.
+    pub fn summary<'a, E: Aggregator>(
+        &'a self,
+        results: Results<'a, E>,
+        options: &'a E::Options,
+        ranking: Option<&Ranking>,
+    ) -> Summary<'a, E> {
+        let mut groups: BTreeMap<&[u8], (E, Results<E>)> = BTreeMap::new();
+        for (station_name, entry) in results {
+            let (group_entry, stations) = groups
+                .entry(self.get_group(station_name))
+                .or_insert_with(|| (E::empty(options), Vec::new()));
+            group_entry.merge(entry);
+            stations.push((station_name, entry));
+        }
+        let mut groups: Vec<_> = groups.into_iter().collect();
+        if let Some(ranking) = ranking {
+            ranking.select(&mut groups, |(name, (entry, _))| (name, entry));
+        }
+        let rows = groups
+            .into_iter()
+            .map(|(group_name, (group_entry, stations))| {
+                let mut row = Row::new(group_name, Some(Cow::Owned(group_entry)));
+                if self.drill_down {
+                    row.children = stations
+                        .into_iter()
+                        .map(|(name, entry)| Row::new(name, Some(Cow::Borrowed(entry))))
+                        .collect();
+                }
+                row
+            });
+        Summary {
+            levels: &["group", "station"],
+            rows: rows.collect(),
+            options,
+        }
+    }
+}
@@ -0,0 +1,189 @@
+use std::{
+    cmp::Ordering,
+    fmt::{self, Display, Formatter},
+    io::{self, Write},
+    str::FromStr,
+};
+
+use crate::{
+    my_hashmap::{Aggregator, Count, StationEntry, Tenths, Unit, Value},
+    partial::Input,
+    ranking::Metric,
+};
+
+// one bucket for every measurement in -999..=999
+const BUCKETS: usize = 1999;
+const BUCKET_OFFSET: i32 = 999;
+
+/// A percentile in hundredths of a percent, so `99.99` is exact and the rank needs no floats.
+#[derive(Clone, Copy, PartialEq)]
+pub struct Percentile(u32);
+
+impl FromStr for Percentile {
+    type Err = String;
+
+    /// Accepts up to two decimals, in `(0, 100]`.
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("invalid percentile: {text}");
+        let (whole, fraction) = text.split_once('.').unwrap_or((text, ""));
+        if fraction.len() > 2 || !fraction.bytes().all(|c| c.is_ascii_digit()) {
+            return Err(invalid());
+        }
+        let whole: u32 = whole.parse().map_err(|_| invalid())?;
+        let fraction = format!("{fraction:0<2}").parse::<u32>().unwrap();
+        match whole.checked_mul(100).and_then(|w| w.checked_add(fraction)) {
+            Some(hundredths @ 1..=10_000) => Ok(Percentile(hundredths)),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+impl Display for Percentile {
+    /// As few decimals as needed, e.g. `50`, `99.9` or `99.99`.
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let (whole, fraction) = (self.0 / 100, self.0 % 100);
+        match fraction {
+            0 => write!(f, "{whole}"),
+            _ if fraction % 10 == 0 => write!(f, "{whole}.{}", fraction / 10),
+            _ => write!(f, "{whole}.{fraction:02}"),
+        }
+    }
+}
+
+impl Percentile {
+    pub fn hundredths(self) -> u32 {
+        self.0
+    }
+
+    pub fn from_hundredths(hundredths: u32) -> Result<Percentile, String> {
+        match hundredths {
+            1..=10_000 => Ok(Percentile(hundredths)),
+            _ => Err(format!("invalid percentile: {hundredths} hundredths")),
+        }
+    }
+
+    /// How many of `count` sorted measurements are at or below this percentile, at least 1.
+    pub fn rank(self, count: Count) -> u128 {
+        (count as u128 * self.0 as u128).div_ceil(10_000)
+    }
+}
+
+/// `StationEntry` plus a count for every possible measurement, for exact percentiles. The
+/// histogram is only allocated once the station has a measurement, as most PHF slots never do.
+#[derive(Clone)]
+pub struct HistogramEntry {
+    pub entry: StationEntry,
+    histogram: Option<Box<[Count; BUCKETS]>>,
+}
+
+impl Aggregator for HistogramEntry {
+    type Options = Vec<Percentile>;
+    type Measurement = i32;
+
+    fn empty(_: &Vec<Percentile>) -> HistogramEntry {
+        HistogramEntry {
+            entry: StationEntry::EMPTY,
+            histogram: None,
+        }
+    }
+
+    fn add_measurement(&mut self, measurement: i32) {
+        self.entry.add_measurement(measurement);
+        let histogram = self.histogram.get_or_insert_with(|| Box::new([0; BUCKETS]));
+        histogram[(measurement + BUCKET_OFFSET) as usize] += 1;
+    }
+
+    fn merge(&mut self, other: &HistogramEntry) {
+        self.entry.merge(&other.entry);
+        let Some(other_histogram) = &other.histogram else {
+            return;
+        };
+        match &mut self.histogram {
+            Some(histogram) => {
+                for (count, other_count) in histogram.iter_mut().zip(other_histogram.iter()) {
+                    *count += other_count;
+                }
+            }
+            None => self.histogram = Some(other_histogram.clone()),
+        }
+    }
+
+    fn count(&self) -> Count {
+        self.entry.count
+    }
+
+    fn compare(&self, other: &HistogramEntry, metric: Metric) -> Ordering {
+        metric.compare(&self.entry, &other.entry)
+    }
+
+    fn write_result(&self, out: &mut impl Write, percentiles: &Vec<Percentile>) -> io::Result<()> {
+        write!(out, "{}", self.entry)?;
+        for &percentile in percentiles {
+            write!(out, "/{}", self.get_percentile(percentile))?;
+        }
+        Ok(())
+    }
+
+    fn field_names(percentiles: &Vec<Percentile>) -> Vec<(String, Unit)> {
+        let mut names = StationEntry::field_names(&());
+        names.extend(percentiles.iter().map(|p| (format!("p{p}"), Unit::Tenths)));
+        names
+    }
+
+    /// Only the buckets with measurements, as most of them are empty.
+    fn serialize(&self, out: &mut Vec<u8>) {
+        self.entry.serialize(out);
+        let histogram = self.histogram.iter().flat_map(|histogram| histogram.iter());
+        let buckets = histogram.enumerate().filter(|(_, count)| **count != 0);
+        out.extend_from_slice(&(buckets.clone().count() as u16).to_le_bytes());
+        for (bucket, &count) in buckets {
+            out.extend_from_slice(&(bucket as u16).to_le_bytes());
+            out.extend_from_slice(&count.to_le_bytes());
+        }
+    }
+
+    fn deserialize(input: &mut Input, _: &Vec<Percentile>) -> Result<HistogramEntry, String> {
+        let mut entry = HistogramEntry {
+            entry: StationEntry::deserialize(input, &())?,
+            histogram: None,
+        };
+        for _ in 0..u16::from_le_bytes(input.array()?) {
+            let bucket = u16::from_le_bytes(input.array()?) as usize;
+            let histogram = entry
+                .histogram
+                .get_or_insert_with(|| Box::new([0; BUCKETS]));
+            *histogram
+                .get_mut(bucket)
+                .ok_or("histogram bucket out of range")? = input.count()?;
+        }
+        Ok(entry)
+    }
+
+    fn fields(&self, percentiles: &Vec<Percentile>) -> Vec<Value> {
+        let mut fields = self.entry.fields(&());
+        fields.extend(
+            percentiles
+                .iter()
+                .map(|&p| Value::Tenths(self.get_percentile(p))),
+        );
+        fields
+    }
+}
+
+impl HistogramEntry {
+    /// Nearest rank percentile: the smallest measurement that at least `percentile` of the
+    /// measurements are less than or equal to. The station must have a measurement.
+    pub fn get_percentile(&self, percentile: Percentile) -> Tenths {
+        let histogram = self.histogram.as_deref().expect("no measurements");
+        let rank = percentile.rank(self.entry.count);
+        let mut seen = 0;
+        let first = (self.entry.min as i32 + BUCKET_OFFSET) as usize;
+        for (bucket, &count) in histogram.iter().enumerate().skip(first) {
+            seen += count as u128;
+            if seen >= rank {
+                return Tenths(bucket as i64 - BUCKET_OFFSET as i64);
+            }
+        }
+        unreachable!("the histogram holds every measurement")
+    }
+}
@@ -0,0 +1,28 @@
+//! Aggregation of `station;measurement` lines into per station statistics, used by the binary
+//! and available to other programs.
+
+#[cfg(feature = "arrow")]
+mod arrow_export;
+mod capi;
+pub mod checkpoint;
+pub mod columns;
+pub mod filter;
+pub mod find_phf;
+mod follow;
+pub mod group_by;
+pub mod histogram;
+pub mod merge;
+pub mod my_hashmap;
+pub mod my_phf;
+pub mod output;
+pub mod partial;
+pub mod phf_search;
+#[cfg(feature = "python")]
+mod python;
+pub mod ranking;
+mod server;
+pub mod sketch;
+pub mod sort_order;
+pub mod station_names;
+pub mod time_buckets;
+pub mod use_phf;
@@ -3,15 +3,28 @@ static GLOBAL: jemallocator::Jemalloc = jemallocator::Jemalloc;
 
 use std::io::Read;
 
-mod my_phf;
-mod station_names;
-mod use_phf;
+use one_billion_row_challange::{find_phf, merge, use_phf};
 
 fn main() {
+    let mut args = std::env::args().skip(1).peekable();
+    match args.peek().map(String::as_str) {
+        Some("find-phf") => return find_phf::run(args.skip(1)),
+        Some("merge") => return merge::run(args.skip(1)),
+        Some("aggregate") => _ = args.next(),
+        _ => {}
+    }
     let (mut reader, writer) = std::io::pipe().unwrap();
+    if std::env::args().any(|arg| arg == "--follow" || arg == "--serve") {
+        // following and serving never end, and signals are sent to the process that was started
+        return use_phf::run(writer, args);
+    }
     if unsafe { libc::fork() } == 0 {
-        use_phf::run(writer);
+        use_phf::run(writer, args);
     } else {
-        _ = reader.read_exact(&mut [0u8]);
+        // without this the pipe stays open after the child exits, even if it exits early
+        drop(writer);
+        if reader.read_exact(&mut [0u8]).is_err() {
+            std::process::exit(1);
+        }
     }
 }
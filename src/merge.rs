@@ -0,0 +1,75 @@
+use std::collections::BTreeMap;
+
+use crate::{
+    my_hashmap::Aggregator,
+    partial::{self, Input},
+    use_phf::{Fields, Report, Task, dispatch},
+};
+
+/// `merge [OPTIONS] PARTIAL...`: merges partial summaries written by `--partial`, which must
+/// have the same statistics and columns, and writes the results the way a single run over all
+/// of their input would. Takes the output options of a run, including `--partial` to merge in
+/// several steps.
+pub fn run(mut args: impl Iterator<Item = String>) {
+    let mut report = Report::default();
+    let mut paths = Vec::new();
+    while let Some(arg) = args.next() {
+        if !report.parse_arg(&arg, &mut args) {
+            assert!(!arg.starts_with("--"), "unknown argument: {arg}");
+            paths.push(arg);
+        }
+    }
+    assert!(!paths.is_empty(), "no partial summaries to merge");
+    let files: Vec<Vec<u8>> = paths
+        .iter()
+        .map(|path| std::fs::read(path).unwrap_or_else(|error| panic!("{path}: {error}")))
+        .collect();
+    let mut partials = Vec::new();
+    for (path, bytes) in paths.iter().zip(&files) {
+        let (layout, input) =
+            partial::read(bytes).unwrap_or_else(|error| panic!("{path}: {error}"));
+        if partials.is_empty() {
+            report.layout = layout;
+        } else {
+            assert!(
+                layout == report.layout,
+                "{path}: partial summary with different statistics or columns"
+            );
+        }
+        partials.push((path.as_str(), input));
+    }
+    report.finish();
+    dispatch(
+        &report.layout,
+        Merge {
+            partials: &partials,
+            report: &report,
+        },
+    );
+}
+
+struct Merge<'a> {
+    partials: &'a [(&'a str, Input<'a>)],
+    report: &'a Report,
+}
+
+impl Task for Merge<'_> {
+    fn run<E: Aggregator<Measurement: Fields>>(self, options: &E::Options) {
+        let mut stations: BTreeMap<&[u8], E> = BTreeMap::new();
+        for &(path, input) in self.partials {
+            let entries = partial::read_entries::<E>(input, options)
+                .unwrap_or_else(|error| panic!("{path}: {error}"));
+            for (name, entry) in entries {
+                match stations.get_mut(name) {
+                    Some(station) => station.merge(&entry),
+                    None => _ = stations.insert(name, entry),
+                }
+            }
+        }
+        let results = stations
+            .iter()
+            .map(|(&name, entry)| (name, entry))
+            .collect();
+        self.report.write_results(results, options);
+    }
+}
@@ -14,10 +14,10 @@ use rustc_hash::FxHashMap;
 use memchr::memchr;
 
 struct StationEntry {
-    sum: i32,
+    sum: i64,
     min: i32,
     max: i32,
-    count: i32,
+    count: u64,
 }
 
 #[derive(Eq)]
@@ -167,11 +167,11 @@ fn process_chunk(chunk: &[u8], summary: &Mutex<FxHashMap<StationName, StationEnt
                 if measurement > e.max {
                     e.max = measurement;
                 }
-                e.sum += measurement;
+                e.sum += measurement as i64;
                 e.count += 1;
             })
             .or_insert(StationEntry {
-                sum: measurement,
+                sum: measurement as i64,
                 min: measurement,
                 max: measurement,
                 count: 1,
@@ -1,7 +1,9 @@
 use std::{
     arch::x86_64::{__m256i, _mm256_loadu_si256},
+    cmp::Ordering,
+    fmt::{self, Display, Formatter},
     hash::{Hash, Hasher},
-    mem::{MaybeUninit, transmute},
+    io::{self, Write},
     ptr::null,
     slice::from_raw_parts,
     str::FromStr,
@@ -9,16 +11,335 @@ use std::{
 
 use rustc_hash::FxHasher;
 
-const LOG_SIZE: usize = 14; // 16K entries, must support at least 10,000
+use crate::{partial::Input, ranking::Metric};
+
+const LOG_SIZE: usize = 14; // 16K entries, must support at least 10,000 without growing
 const SIZE: usize = 1 << LOG_SIZE;
-const MASK: usize = SIZE - 1;
 
+// 64 bits, so a station's count cannot wrap around however large the input is
+pub type Count = u64;
+
+/// Per station statistics, accumulated from measurements in tenths and merged across chunks.
+pub trait Aggregator: Clone + Send {
+    /// Settings given on the command line, e.g. which percentiles to print.
+    type Options: Sync;
+    /// What is parsed from each line after the station name, a single measurement by default.
+    type Measurement: Copy;
+
+    /// An entry without measurements.
+    fn empty(options: &Self::Options) -> Self;
+
+    fn add_measurement(&mut self, measurement: Self::Measurement);
+    fn merge(&mut self, other: &Self);
+    fn count(&self) -> Count;
+    /// Ascending order by `metric`, for `--top`, `--bottom` and `--sort`.
+    fn compare(&self, other: &Self, metric: Metric) -> Ordering;
+    /// Writes the statistics that follow `name=` in the output.
+    fn write_result(&self, out: &mut impl Write, options: &Self::Options) -> io::Result<()>;
+    /// Names and units of the values in `fields`, for the headers, keys and column types of
+    /// the structured formats.
+    fn field_names(options: &Self::Options) -> Vec<(String, Unit)>;
+    /// The statistics of `write_result` and the count as separate values.
+    fn fields(&self, options: &Self::Options) -> Vec<Value>;
+    /// Appends the entry to a partial summary, see `partial::write`.
+    fn serialize(&self, out: &mut Vec<u8>);
+    /// Reads an entry written by `serialize`.
+    fn deserialize(input: &mut Input, options: &Self::Options) -> Result<Self, String>;
+}
+
+// measurements are in -999..=999, so min and max fit in 16 bits
+#[derive(Clone, Copy)]
 pub struct StationEntry {
-    pub sum: i32,
-    pub min: i32,
-    pub max: i32,
-    pub count: i32,
+    pub sum: i64,
+    pub count: Count,
+    pub min: i16,
+    pub max: i16,
+}
+
+impl Aggregator for StationEntry {
+    type Options = ();
+    type Measurement = i32;
+
+    fn empty(_: &()) -> StationEntry {
+        StationEntry::EMPTY
+    }
+
+    #[cfg(not(feature = "overflow-checks"))]
+    fn add_measurement(&mut self, measurement: i32) {
+        self.sum += measurement as i64;
+        self.count += 1;
+        self.update_min_max(measurement as i16, measurement as i16);
+    }
+    #[cfg(feature = "overflow-checks")]
+    fn add_measurement(&mut self, measurement: i32) {
+        assert!(
+            (-999..=999).contains(&measurement),
+            "measurement out of range"
+        );
+        self.sum = self
+            .sum
+            .checked_add(measurement as i64)
+            .expect("sum overflowed");
+        self.count = self.count.checked_add(1).expect("count overflowed");
+        self.update_min_max(measurement as i16, measurement as i16);
+    }
+
+    #[cfg(not(feature = "overflow-checks"))]
+    fn merge(&mut self, other: &StationEntry) {
+        self.sum += other.sum;
+        self.count += other.count;
+        self.update_min_max(other.min, other.max);
+    }
+    #[cfg(feature = "overflow-checks")]
+    fn merge(&mut self, other: &StationEntry) {
+        self.sum = self.sum.checked_add(other.sum).expect("sum overflowed");
+        self.count = self
+            .count
+            .checked_add(other.count)
+            .expect("count overflowed");
+        self.update_min_max(other.min, other.max);
+    }
+
+    fn count(&self) -> Count {
+        self.count
+    }
+
+    fn compare(&self, other: &StationEntry, metric: Metric) -> Ordering {
+        metric.compare(self, other)
+    }
+
+    fn write_result(&self, out: &mut impl Write, _: &()) -> io::Result<()> {
+        write!(out, "{self}")
+    }
+
+    fn field_names(_: &()) -> Vec<(String, Unit)> {
+        [
+            ("min", Unit::Tenths),
+            ("mean", Unit::Tenths),
+            ("max", Unit::Tenths),
+            ("count", Unit::Count),
+        ]
+        .map(|(name, unit)| (name.to_string(), unit))
+        .to_vec()
+    }
+
+    fn fields(&self, _: &()) -> Vec<Value> {
+        let (min, mean, max) = self.get_result();
+        vec![
+            Value::Tenths(min),
+            Value::Tenths(mean),
+            Value::Tenths(max),
+            Value::Count(self.count),
+        ]
+    }
+
+    fn serialize(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.sum.to_le_bytes());
+        out.extend_from_slice(&self.count.to_le_bytes());
+        out.extend_from_slice(&self.min.to_le_bytes());
+        out.extend_from_slice(&self.max.to_le_bytes());
+    }
+
+    fn deserialize(input: &mut Input, _: &()) -> Result<StationEntry, String> {
+        Ok(StationEntry {
+            sum: i64::from_le_bytes(input.array()?),
+            count: input.count()?,
+            min: i16::from_le_bytes(input.array()?),
+            max: i16::from_le_bytes(input.array()?),
+        })
+    }
+}
+
+impl StationEntry {
+    pub const EMPTY: StationEntry = StationEntry {
+        sum: 0,
+        count: 0,
+        min: 1000,
+        max: -1000,
+    };
+
+    fn update_min_max(&mut self, min: i16, max: i16) {
+        if max > self.max {
+            self.max = max;
+        }
+        if min < self.min {
+            self.min = min;
+        }
+    }
+
+    /// Min, mean and max, with the mean rounded half up (towards positive infinity) the way
+    /// the reference implementation's `Math.round` does.
+    pub fn get_result(&self) -> (Tenths, Tenths, Tenths) {
+        let count = self.count as i64;
+        (
+            Tenths(self.min as i64),
+            Tenths((2 * self.sum + count).div_euclid(2 * count)),
+            Tenths(self.max as i64),
+        )
+    }
+}
+
+impl Display for StationEntry {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let (min, mean, max) = self.get_result();
+        write!(f, "{min}/{mean}/{max}")
+    }
+}
+
+/// `StationEntry` plus the sum of squares for `--stats`, kept separate so the plain run does not
+/// carry it in its entries.
+#[derive(Clone, Copy)]
+pub struct StatsEntry {
+    pub entry: StationEntry,
+    pub sum_squares: u64,
+}
+
+impl Aggregator for StatsEntry {
+    type Options = ();
+    type Measurement = i32;
+
+    fn empty(_: &()) -> StatsEntry {
+        StatsEntry {
+            entry: StationEntry::EMPTY,
+            sum_squares: 0,
+        }
+    }
+
+    fn add_measurement(&mut self, measurement: i32) {
+        self.entry.add_measurement(measurement);
+        self.add_squares((measurement * measurement) as u64);
+    }
+
+    fn merge(&mut self, other: &StatsEntry) {
+        self.entry.merge(&other.entry);
+        self.add_squares(other.sum_squares);
+    }
+
+    fn count(&self) -> Count {
+        self.entry.count
+    }
+
+    fn compare(&self, other: &StatsEntry, metric: Metric) -> Ordering {
+        metric.compare(&self.entry, &other.entry)
+    }
+
+    fn write_result(&self, out: &mut impl Write, _: &()) -> io::Result<()> {
+        write!(out, "{self}")
+    }
+
+    fn field_names(_: &()) -> Vec<(String, Unit)> {
+        let mut names = StationEntry::field_names(&());
+        names.push(("stddev".to_string(), Unit::Tenths));
+        names
+    }
+
+    fn fields(&self, _: &()) -> Vec<Value> {
+        let mut fields = self.entry.fields(&());
+        fields.push(Value::Tenths(self.get_stddev()));
+        fields
+    }
+
+    fn serialize(&self, out: &mut Vec<u8>) {
+        self.entry.serialize(out);
+        out.extend_from_slice(&self.sum_squares.to_le_bytes());
+    }
+
+    fn deserialize(input: &mut Input, _: &()) -> Result<StatsEntry, String> {
+        Ok(StatsEntry {
+            entry: StationEntry::deserialize(input, &())?,
+            sum_squares: u64::from_le_bytes(input.array()?),
+        })
+    }
+}
+
+impl StatsEntry {
+    #[cfg(not(feature = "overflow-checks"))]
+    fn add_squares(&mut self, squares: u64) {
+        self.sum_squares += squares;
+    }
+    #[cfg(feature = "overflow-checks")]
+    fn add_squares(&mut self, squares: u64) {
+        self.sum_squares = self
+            .sum_squares
+            .checked_add(squares)
+            .expect("sum of squares overflowed");
+    }
+
+    /// Population standard deviation, rounded half up. `count^2 * variance` is exact in integer
+    /// tenths, so the only rounding is the final one.
+    pub fn get_stddev(&self) -> Tenths {
+        let count = self.entry.count as i128;
+        let scaled_variance = count * self.sum_squares as i128 - (self.entry.sum as i128).pow(2);
+        // floor(sqrt(v) / count + 1/2) == floor((isqrt(4v) + count) / 2count)
+        Tenths(((4 * scaled_variance).isqrt() + count).div_euclid(2 * count) as i64)
+    }
+}
+
+impl Display for StatsEntry {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.entry, self.get_stddev())
+    }
+}
+
+/// A value in tenths, printed with exactly one decimal and never as `-0.0`.
+#[derive(Clone, Copy)]
+pub struct Tenths(pub i64);
+
+impl Display for Tenths {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let sign = if self.0 < 0 { "-" } else { "" };
+        let abs = self.0.unsigned_abs();
+        write!(f, "{sign}{}.{}", abs / 10, abs % 10)
+    }
+}
+
+/// A value of any magnitude and precision, for measurements that do not fit in `Tenths`,
+/// printed with `decimals` decimals and never as `-0`.
+#[derive(Clone, Copy)]
+pub struct Decimal {
+    pub value: f64,
+    /// How many decimals the measurement was written with, or the most of any measurement a
+    /// statistic was calculated from.
+    pub decimals: u8,
+}
+
+impl Display for Decimal {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let text = format!("{:.*}", self.decimals as usize, self.value);
+        match text.strip_prefix('-') {
+            // a negative value that rounds to zero
+            Some(abs) if abs.bytes().all(|c| matches!(c, b'0' | b'.')) => f.write_str(abs),
+            _ => f.write_str(&text),
+        }
+    }
+}
+
+/// What a statistic of `Aggregator::fields` counts.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Unit {
+    Tenths,
+    Decimal,
+    Count,
+}
+
+/// One statistic of `Aggregator::fields`, printed as a number.
+#[derive(Clone, Copy)]
+pub enum Value {
+    Tenths(Tenths),
+    Decimal(Decimal),
+    Count(Count),
+}
+
+impl Display for Value {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Tenths(tenths) => tenths.fmt(f),
+            Value::Decimal(decimal) => decimal.fmt(f),
+            Value::Count(count) => count.fmt(f),
+        }
+    }
 }
+
 #[derive(Eq, Copy, Clone)]
 pub struct StationName {
     pub ptr: *const u8,
@@ -37,6 +358,9 @@ impl StationName {
         if self.len != other.len {
             return false;
         }
+        if self.len >= 32 {
+            return self.as_bytes() == other.as_bytes();
+        }
         let s = unsafe { _mm256_loadu_si256(self.ptr as *const __m256i) };
         let o = unsafe { _mm256_loadu_si256(other.ptr as *const __m256i) };
         let mask = (1 << self.len.max(other.len)) - 1;
@@ -51,6 +375,9 @@ impl StationName {
         if self.len != other.len {
             return false;
         }
+        if self.len >= 32 {
+            return self.as_bytes() == other.as_bytes();
+        }
         let s = unsafe { _mm256_loadu_si256(self.ptr as *const __m256i) };
         let o = unsafe { _mm256_loadu_si256(other.ptr as *const __m256i) };
         let mask = (1 << self.len) - 1;
@@ -59,9 +386,11 @@ impl StationName {
     }
     #[cfg(not(target_feature = "avx2"))]
     fn eq_inner(&self, other: &Self) -> bool {
-        let self_slice = unsafe { from_raw_parts(self.ptr, self.len as usize) };
-        let other_slice = unsafe { from_raw_parts(other.ptr, other.len as usize) };
-        self_slice == other_slice
+        self.as_bytes() == other.as_bytes()
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        unsafe { from_raw_parts(self.ptr, self.len as usize) }
     }
 }
 impl PartialEq for StationName {
@@ -73,7 +402,14 @@ impl PartialEq for StationName {
 impl Hash for StationName {
     fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
         let ptr = self.ptr as *const u32;
-        unsafe { ptr.read_unaligned() }.hash(state);
+        let sample = unsafe { ptr.read_unaligned() };
+        // names shorter than the sample would otherwise hash the measurement after them
+        let mask = if self.len >= 4 {
+            !0
+        } else {
+            (1 << (self.len * 8)) - 1
+        };
+        (sample & mask).hash(state);
     }
 }
 impl From<StationName> for String {
@@ -83,101 +419,97 @@ impl From<StationName> for String {
     }
 }
 
-pub struct MyHashMap {
-    names: Box<[StationName; SIZE]>,
-    entries: Box<[StationEntry; SIZE]>,
+/// A key of `MyHashMap`, with a reserved value that marks free slots.
+pub trait MapKey: Copy + Eq + Hash {
+    const FREE: Self;
+
+    fn is_free(&self) -> bool;
 }
 
-impl MyHashMap {
-    pub fn new() -> MyHashMap {
-        let mut names;
-        let mut entries;
-        unsafe {
-            names = Box::<[MaybeUninit<StationName>; SIZE]>::new_uninit().assume_init();
-            entries = Box::<[MaybeUninit<StationEntry>; SIZE]>::new_uninit().assume_init();
-        }
-        for name in names.iter_mut() {
-            name.write(StationName {
-                ptr: null(),
-                len: 0,
-            });
-        }
-        for entry in entries.iter_mut() {
-            entry.write(StationEntry {
-                min: 1000,
-                max: -1000,
-                sum: 0,
-                count: 0,
-            });
-        }
+impl MapKey for StationName {
+    const FREE: StationName = StationName {
+        ptr: null(),
+        len: 0,
+    };
+
+    fn is_free(&self) -> bool {
+        self.ptr.is_null()
+    }
+}
+
+/// Linear probing map, starting with room for 16K keys and doubling when three quarters full.
+pub struct MyHashMap<E: Aggregator = StationEntry, K: MapKey = StationName> {
+    keys: Box<[K]>,
+    entries: Box<[E]>,
+    len: usize,
+    // what new slots are filled with when the map grows
+    empty: E,
+}
+
+impl<E: Aggregator, K: MapKey> MyHashMap<E, K> {
+    pub fn new(options: &E::Options) -> MyHashMap<E, K> {
+        let empty = E::empty(options);
         MyHashMap {
-            names: unsafe {
-                transmute::<Box<[MaybeUninit<StationName>; SIZE]>, Box<[StationName; SIZE]>>(names)
-            },
-            entries: unsafe {
-                transmute::<Box<[MaybeUninit<StationEntry>; SIZE]>, Box<[StationEntry; SIZE]>>(
-                    entries,
-                )
-            },
+            keys: vec![K::FREE; SIZE].into_boxed_slice(),
+            entries: vec![empty.clone(); SIZE].into_boxed_slice(),
+            len: 0,
+            empty,
         }
     }
 
-    pub fn insert_measurement(&mut self, name: StationName, measurement: i32) {
+    fn get_entry(&mut self, key: K) -> &mut E {
+        if self.len * 4 >= self.keys.len() * 3 {
+            self.grow();
+        }
+        let mask = self.keys.len() - 1;
         let mut hasher = FxHasher::default();
-        name.hash(&mut hasher);
+        key.hash(&mut hasher);
         let mut hash = hasher.finish() as usize;
-        let entry = unsafe {
+        unsafe {
             loop {
-                let index = hash & MASK;
-                let potential_name = self.names.get_unchecked_mut(index);
-                if potential_name.ptr.is_null() {
-                    *potential_name = name;
+                let index = hash & mask;
+                let potential_key = self.keys.get_unchecked_mut(index);
+                if potential_key.is_free() {
+                    *potential_key = key;
+                    self.len += 1;
                     break self.entries.get_unchecked_mut(index);
                 }
-                if *potential_name == name {
+                if *potential_key == key {
                     break self.entries.get_unchecked_mut(index);
                 }
                 hash = hash.wrapping_add(1);
             }
-        };
-        entry.sum += measurement;
-        entry.count += 1;
-        if measurement > entry.max {
-            entry.max = measurement;
-        }
-        if measurement < entry.min {
-            entry.min = measurement;
         }
     }
 
-    pub fn merge_entry(&mut self, name: &StationName, other_entry: &StationEntry) {
-        let mut hasher = FxHasher::default();
-        name.hash(&mut hasher);
-        let mut hash = hasher.finish() as usize;
-        let entry = unsafe {
-            loop {
-                let index = hash & MASK;
-                let potential_name = self.names.get_unchecked_mut(index & MASK);
-                if potential_name.ptr.is_null() {
-                    *potential_name = *name;
-                    break self.entries.get_unchecked_mut(index);
-                }
-                if *potential_name == *name {
-                    break self.entries.get_unchecked_mut(index);
-                }
-                hash = hash.wrapping_add(1);
+    #[cold]
+    fn grow(&mut self) {
+        let size = self.keys.len() * 2;
+        let keys = std::mem::replace(&mut self.keys, vec![K::FREE; size].into_boxed_slice());
+        let entries = std::mem::replace(
+            &mut self.entries,
+            vec![self.empty.clone(); size].into_boxed_slice(),
+        );
+        self.len = 0;
+        for (key, entry) in keys.iter().zip(entries) {
+            if !key.is_free() {
+                *self.get_entry(*key) = entry;
             }
-        };
-        entry.sum += other_entry.sum;
-        entry.count += other_entry.count;
-        entry.max = entry.max.max(other_entry.max);
-        entry.min = entry.min.min(other_entry.min);
+        }
+    }
+
+    pub fn insert_measurement(&mut self, key: K, measurement: E::Measurement) {
+        self.get_entry(key).add_measurement(measurement);
+    }
+
+    pub fn merge_entry(&mut self, key: &K, other_entry: &E) {
+        self.get_entry(*key).merge(other_entry);
     }
 
-    pub fn iter(&self) -> impl Iterator<Item = (&StationName, &StationEntry)> {
-        self.names
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &E)> {
+        self.keys
             .iter()
             .zip(self.entries.iter())
-            .filter(|(name, _)| !name.ptr.is_null())
+            .filter(|(key, _)| !key.is_free())
     }
 }
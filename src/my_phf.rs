@@ -1,68 +1,202 @@
-use std::{
-    io::Write,
-    mem::{MaybeUninit, transmute},
+use std::arch::x86_64::_bzhi_u64;
+
+#[cfg(target_feature = "avx2")]
+use std::arch::x86_64::{
+    __m256i, _mm256_cmpeq_epi8, _mm256_load_si256, _mm256_loadu_si256, _mm256_movemask_epi8,
 };
 
-use crate::station_names::STATION_NAMES;
+use crate::{
+    filter::Filter,
+    my_hashmap::{Aggregator, MyHashMap, StationEntry, StationName},
+    phf_search::{self, PhfHash, PhfParameters, SearchError, get_name_sample},
+    station_names::{
+        STATION_NAMES, STATION_NAMES_HASH, STATION_NAMES_OFFSET, STATION_NAMES_SIZE,
+        STATION_NAMES_WIDTH,
+    },
+};
 
-const SIZE: usize = 13779;
+// the last byte of a slot name holds its length
+const MAX_NAME_LEN: usize = 31;
+const UNUSED_SLOT: u8 = u8::MAX;
 
-pub struct StationEntry {
-    pub sum: i32,
-    pub min: i32,
-    pub max: i32,
-    pub count: i32,
-}
+/// The name expected in a PHF slot, checked on every insert so unknown names are not counted as
+/// whichever known name they collide with.
+#[derive(Clone, Copy)]
+#[repr(C, align(32))]
+struct SlotName([u8; MAX_NAME_LEN + 1]);
 
-impl StationEntry {
-    fn get_result(&self) -> (f32, f32, f32) {
-        (
-            self.min as f32 / 10f32,
-            self.sum as f32 / (self.count as f32 * 10f32),
-            self.max as f32 / 10f32,
-        )
+impl SlotName {
+    fn new(name: &[u8]) -> SlotName {
+        let mut slot_name = SlotName([0; MAX_NAME_LEN + 1]);
+        slot_name.0[..name.len()].copy_from_slice(name);
+        slot_name.0[MAX_NAME_LEN] = name.len() as u8;
+        slot_name
+    }
+
+    fn len(&self) -> usize {
+        self.0[MAX_NAME_LEN] as usize
     }
+
+    /// The name must be followed by enough readable bytes to fill a vector.
+    #[cfg(target_feature = "avx2")]
+    #[target_feature(enable = "avx2")]
+    fn matches(&self, name: &[u8]) -> bool {
+        if self.len() != name.len() {
+            return false;
+        }
+        let s = unsafe { _mm256_load_si256(self.0.as_ptr() as *const __m256i) };
+        let o = unsafe { _mm256_loadu_si256(name.as_ptr() as *const __m256i) };
+        let mask = (1 << name.len()) - 1;
+        let diff = _mm256_movemask_epi8(_mm256_cmpeq_epi8(s, o)) as u32;
+        diff & mask == mask
+    }
+    #[cfg(not(target_feature = "avx2"))]
+    fn matches(&self, name: &[u8]) -> bool {
+        self.len() == name.len() && self.0[..name.len()] == *name
+    }
+}
+
+/// One name per line.
+pub fn read_names_file(path: &str) -> Vec<Vec<u8>> {
+    std::fs::read(path)
+        .expect("names file not found")
+        .split(|&c| c == b'\n')
+        .filter(|name| !name.is_empty())
+        .map(|name| name.to_vec())
+        .collect()
 }
 
-pub fn get_name_index(name: &[u8]) -> usize {
-    const OFFSET: usize = 1;
-    let ptr = unsafe { name.as_ptr().add(OFFSET) } as *const u64;
-    let mut sample = unsafe { ptr.read_unaligned() };
-    let len = (name.len() - 1).min(8);
-    let to_mask = len * 8;
-    let mask = u64::MAX >> (64 - to_mask);
-    sample &= mask;
-    sample as usize % SIZE
+/// A perfect hash function over a known set of names, see `PhfParameters`.
+pub struct Phf {
+    offset: usize,
+    width: usize,
+    hash: PhfHash,
+    size: usize,
+    // `u128::MAX / size + 1`, used to calculate the modulo without a division
+    multiplier: u128,
+    slot_names: Box<[SlotName]>,
+    // sorted by name, for printing
+    names: Vec<(Box<[u8]>, usize)>,
 }
 
-// pub fn get_name_index(name: &[u8]) -> usize {
-//     gperf::hash(name, name.len())
-// }
+impl Phf {
+    /// # Panics
+    /// If any two of the names are mapped to the same index, or a name is longer than 31 bytes.
+    pub fn new(names: Vec<Vec<u8>>, parameters: PhfParameters) -> Phf {
+        let PhfParameters {
+            offset,
+            width,
+            hash,
+            size,
+        } = parameters;
+        let mut phf = Phf {
+            offset,
+            width,
+            hash,
+            size,
+            multiplier: u128::MAX / size as u128 + 1,
+            slot_names: vec![SlotName([UNUSED_SLOT; MAX_NAME_LEN + 1]); size].into_boxed_slice(),
+            names: Vec::new(),
+        };
+        phf.names = names
+            .into_iter()
+            .map(|name| {
+                assert!(name.len() <= MAX_NAME_LEN, "name too long for a PHF slot");
+                let index = phf.fast_mod(hash.key(get_name_sample(&name, offset, width)));
+                assert!(
+                    phf.slot_names[index].len() == UNUSED_SLOT as usize,
+                    "not a perfect hash"
+                );
+                phf.slot_names[index] = SlotName::new(&name);
+                (name.into_boxed_slice(), index)
+            })
+            .collect();
+        phf.names.sort_unstable();
+        phf
+    }
+
+    pub fn station_names() -> Phf {
+        Phf::new(
+            STATION_NAMES.iter().map(|name| name.to_vec()).collect(),
+            PhfParameters {
+                offset: STATION_NAMES_OFFSET,
+                width: STATION_NAMES_WIDTH,
+                hash: STATION_NAMES_HASH,
+                size: STATION_NAMES_SIZE,
+            },
+        )
+    }
+
+    /// Fails if `phf_search::search` finds no perfect hash function for the names. Names longer
+    /// than 31 bytes are left out, `MyPHFMap` keeps them in its overflow map.
+    pub fn build(mut names: Vec<Vec<u8>>) -> Result<Phf, SearchError> {
+        names.retain(|name| name.len() <= MAX_NAME_LEN);
+        names.sort_unstable();
+        names.dedup();
+        phf_search::search(&names).map(|parameters| Phf::new(names, parameters))
+    }
+
+    /// `build`, or if that fails a PHF without names, so `MyPHFMap` keeps every station in its
+    /// overflow map.
+    pub fn build_or_empty(names: Vec<Vec<u8>>) -> Phf {
+        Phf::build(names).unwrap_or_else(|error| {
+            eprintln!("{error}, every station is kept in a hash map instead");
+            let smallest = PhfParameters {
+                offset: 0,
+                width: 1,
+                hash: PhfHash::Modulo,
+                size: 2,
+            };
+            Phf::new(Vec::new(), smallest)
+        })
+    }
+
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Every name with its slot, sorted by name.
+    pub fn names(&self) -> impl Iterator<Item = (&[u8], usize)> {
+        self.names.iter().map(|(name, index)| (&**name, *index))
+    }
 
-pub struct MyPHFMap {
-    entries: Box<[StationEntry; SIZE]>,
+    fn fast_mod(&self, sample: u64) -> usize {
+        let low_bits = self.multiplier.wrapping_mul(sample as u128);
+        let bottom = ((low_bits as u64 as u128) * self.size as u128) >> 64;
+        let top = (low_bits >> 64) * self.size as u128;
+        ((bottom + top) >> 64) as usize
+    }
+
+    /// The name must be followed by at least `offset + 8` readable bytes.
+    pub fn get_name_index(&self, name: &[u8]) -> usize {
+        let ptr = unsafe { name.as_ptr().add(self.offset) } as *const u64;
+        let sample = unsafe { ptr.read_unaligned() };
+        let len = name.len().saturating_sub(self.offset).min(self.width);
+        let sample = unsafe { _bzhi_u64(sample, len as u32 * 8) };
+        self.fast_mod(self.hash.key(sample))
+    }
+
+    /// The name must be followed by at least 32 readable bytes.
+    pub fn is_name_at(&self, name_index: usize, name: &[u8]) -> bool {
+        unsafe { self.slot_names.get_unchecked(name_index).matches(name) }
+    }
 }
 
-impl MyPHFMap {
-    pub fn new() -> MyPHFMap {
-        let mut entries;
-        unsafe {
-            entries = Box::<[MaybeUninit<StationEntry>; SIZE]>::new_uninit().assume_init();
-        }
-        for entry in entries.iter_mut() {
-            entry.write(StationEntry {
-                min: 1000,
-                max: -1000,
-                sum: 0,
-                count: 0,
-            });
-        }
+pub struct MyPHFMap<'a, E: Aggregator = StationEntry> {
+    phf: &'a Phf,
+    options: &'a E::Options,
+    entries: Box<[E]>,
+    // names that are not part of the PHF, only allocated once one is seen
+    overflow: Option<Box<MyHashMap<E>>>,
+}
+
+impl<'a, E: Aggregator> MyPHFMap<'a, E> {
+    pub fn new(phf: &'a Phf, options: &'a E::Options) -> MyPHFMap<'a, E> {
         MyPHFMap {
-            entries: unsafe {
-                transmute::<Box<[MaybeUninit<StationEntry>; SIZE]>, Box<[StationEntry; SIZE]>>(
-                    entries,
-                )
-            },
+            phf,
+            options,
+            entries: vec![E::empty(options); phf.size()].into_boxed_slice(),
+            overflow: None,
         }
     }
 
@@ -71,52 +205,79 @@ impl MyPHFMap {
     //     unsafe { _mm_prefetch::<_MM_HINT_ET0>(self.entries.as_ptr().add(name_index) as *const i8) };
     // }
 
-    pub fn insert_measurement(&mut self, name: &[u8], measurement: i32) {
-        self.insert_measurement_by_index(get_name_index(name), measurement);
-    }
-    pub fn insert_measurement_by_index(&mut self, name_index: usize, measurement: i32) {
-        let entry = unsafe { self.entries.get_unchecked_mut(name_index) };
-        entry.sum += measurement;
-        entry.count += 1;
-        if measurement > entry.max {
-            entry.max = measurement;
+    pub fn insert_measurement(&mut self, name: &[u8], measurement: E::Measurement) {
+        let name_index = self.phf.get_name_index(name);
+        if self.phf.is_name_at(name_index, name) {
+            self.insert_measurement_by_index(name_index, measurement);
+        } else {
+            self.insert_overflow_measurement(name, measurement);
         }
-        if measurement < entry.min {
-            entry.min = measurement;
+    }
+    #[cold]
+    fn insert_overflow_measurement(&mut self, name: &[u8], measurement: E::Measurement) {
+        self.overflow
+            .get_or_insert_with(|| Box::new(MyHashMap::new(self.options)))
+            .insert_measurement(
+                StationName {
+                    ptr: name.as_ptr(),
+                    len: name.len() as u8,
+                },
+                measurement,
+            );
+    }
+    /// Like `insert_measurement`, but drops the measurement if `filter` rejects the station.
+    pub fn insert_filtered_measurement(
+        &mut self,
+        name: &[u8],
+        measurement: E::Measurement,
+        filter: &Filter,
+    ) {
+        let name_index = self.phf.get_name_index(name);
+        if self.phf.is_name_at(name_index, name) {
+            if filter.accepts_slot(name_index) {
+                self.insert_measurement_by_index(name_index, measurement);
+            }
+        } else if filter.accepts_name(name) {
+            self.insert_overflow_measurement(name, measurement);
         }
     }
+    pub fn insert_measurement_by_index(&mut self, name_index: usize, measurement: E::Measurement) {
+        unsafe { self.entries.get_unchecked_mut(name_index) }.add_measurement(measurement);
+    }
 
     pub fn merge_maps(&mut self, other_map: Self) {
         for (entry, other_entry) in self.entries.iter_mut().zip(other_map.entries.iter()) {
-            if (entry.count != 0) | (other_entry.count != 0) {
-                entry.sum += other_entry.sum;
-                entry.count += other_entry.count;
-                entry.max = entry.max.max(other_entry.max);
-                entry.min = entry.min.min(other_entry.min);
+            if (entry.count() != 0) | (other_entry.count() != 0) {
+                entry.merge(other_entry);
             }
         }
-    }
-
-    pub fn print_results(self) {
-        let mut out = std::io::stdout().lock();
-        let _ = out.write_all(b"{");
-        for station_name in STATION_NAMES[..STATION_NAMES.len() - 1].iter() {
-            let name = unsafe { std::str::from_utf8_unchecked(station_name) };
-            let index = get_name_index(station_name);
-            let entry = unsafe { self.entries.get_unchecked(index) };
-            if entry.count != 0 {
-                let (min, avg, max) = entry.get_result();
-                let _ = out.write_fmt(format_args!("{name}={min:.1}/{avg:.1}/{max:.1}, "));
+        if let Some(other_overflow) = other_map.overflow {
+            let overflow = self
+                .overflow
+                .get_or_insert_with(|| Box::new(MyHashMap::new(self.options)));
+            for (name, entry) in other_overflow.iter() {
+                overflow.merge_entry(name, entry);
             }
         }
-        let station_name = STATION_NAMES[STATION_NAMES.len() - 1];
-        let name = unsafe { std::str::from_utf8_unchecked(station_name) };
-        let index = get_name_index(station_name);
-        let entry = unsafe { self.entries.get_unchecked(index) };
-        if entry.count != 0 {
-            let (min, avg, max) = entry.get_result();
-            let _ = out.write_fmt(format_args!("{name}={min:.1}/{avg:.1}/{max:.1}}}"));
+    }
+
+    /// Every station with a measurement, sorted by name.
+    pub fn results(&self) -> Vec<(&[u8], &E)> {
+        let mut results: Vec<(&[u8], &E)> = self
+            .phf
+            .names
+            .iter()
+            .map(|(station_name, index)| (&**station_name, &self.entries[*index]))
+            .filter(|(_, entry)| entry.count() != 0)
+            .collect();
+        if let Some(overflow) = &self.overflow {
+            results.extend(
+                overflow
+                    .iter()
+                    .map(|(name, entry)| (name.as_bytes(), entry)),
+            );
+            results.sort_unstable_by(|r1, r2| r1.0.cmp(r2.0));
         }
-        _ = out.flush();
+        results
     }
 }
@@ -0,0 +1,146 @@
+use std::{
+    hash::{Hash, Hasher},
+    mem::{MaybeUninit, transmute},
+    ptr::null,
+};
+
+#[cfg(not(target_feature = "avx2"))]
+use std::arch::x86_64::{
+    __m128i, _mm_cmpeq_epi8, _mm_load_si128, _mm_movemask_epi8, _mm_set1_epi8,
+};
+#[cfg(target_feature = "avx2")]
+use std::arch::x86_64::{
+    __m256i, _mm256_cmpeq_epi8, _mm256_load_si256, _mm256_movemask_epi8, _mm256_set1_epi8,
+};
+
+use rustc_hash::FxHasher;
+
+use crate::my_hashmap::{Aggregator, StationEntry, StationName};
+
+const LOG_SIZE: usize = 14; // 16K entries, must support at least 10,000
+const SIZE: usize = 1 << LOG_SIZE;
+#[cfg(target_feature = "avx2")]
+const GROUP_SIZE: usize = 32;
+#[cfg(not(target_feature = "avx2"))]
+const GROUP_SIZE: usize = 16;
+const GROUP_COUNT: usize = SIZE / GROUP_SIZE;
+const GROUP_MASK: usize = GROUP_COUNT - 1;
+// tags only use the low 7 bits, so the high bit marks a free slot
+const EMPTY: u8 = 0x80;
+
+#[derive(Clone, Copy)]
+#[repr(C, align(32))]
+struct ControlGroup([u8; GROUP_SIZE]);
+
+impl ControlGroup {
+    #[cfg(target_feature = "avx2")]
+    #[target_feature(enable = "avx2")]
+    fn match_tag(&self, tag: u8) -> u32 {
+        let group = unsafe { _mm256_load_si256(self.0.as_ptr() as *const __m256i) };
+        _mm256_movemask_epi8(_mm256_cmpeq_epi8(group, _mm256_set1_epi8(tag as i8))) as u32
+    }
+    #[cfg(target_feature = "avx2")]
+    #[target_feature(enable = "avx2")]
+    fn match_empty(&self) -> u32 {
+        let group = unsafe { _mm256_load_si256(self.0.as_ptr() as *const __m256i) };
+        _mm256_movemask_epi8(group) as u32
+    }
+    #[cfg(not(target_feature = "avx2"))]
+    fn match_tag(&self, tag: u8) -> u32 {
+        let group = unsafe { _mm_load_si128(self.0.as_ptr() as *const __m128i) };
+        _mm_movemask_epi8(_mm_cmpeq_epi8(group, _mm_set1_epi8(tag as i8))) as u32
+    }
+    #[cfg(not(target_feature = "avx2"))]
+    fn match_empty(&self) -> u32 {
+        let group = unsafe { _mm_load_si128(self.0.as_ptr() as *const __m128i) };
+        _mm_movemask_epi8(group) as u32
+    }
+}
+
+/// Same interface as `MyHashMap`, but every slot has a 7 bit tag from the hash in a control
+/// byte, and a whole group of control bytes is compared at once, so full name comparisons are
+/// only done on tag matches.
+pub struct MySwissHashMap {
+    control: Box<[ControlGroup; GROUP_COUNT]>,
+    names: Box<[StationName; SIZE]>,
+    entries: Box<[StationEntry; SIZE]>,
+}
+
+impl MySwissHashMap {
+    pub fn new() -> MySwissHashMap {
+        let mut names;
+        let mut entries;
+        unsafe {
+            names = Box::<[MaybeUninit<StationName>; SIZE]>::new_uninit().assume_init();
+            entries = Box::<[MaybeUninit<StationEntry>; SIZE]>::new_uninit().assume_init();
+        }
+        for name in names.iter_mut() {
+            name.write(StationName {
+                ptr: null(),
+                len: 0,
+            });
+        }
+        for entry in entries.iter_mut() {
+            entry.write(StationEntry::EMPTY);
+        }
+        MySwissHashMap {
+            control: Box::new([ControlGroup([EMPTY; GROUP_SIZE]); GROUP_COUNT]),
+            names: unsafe {
+                transmute::<Box<[MaybeUninit<StationName>; SIZE]>, Box<[StationName; SIZE]>>(names)
+            },
+            entries: unsafe {
+                transmute::<Box<[MaybeUninit<StationEntry>; SIZE]>, Box<[StationEntry; SIZE]>>(
+                    entries,
+                )
+            },
+        }
+    }
+
+    fn get_entry(&mut self, name: &StationName) -> &mut StationEntry {
+        let mut hasher = FxHasher::default();
+        name.hash(&mut hasher);
+        let hash = hasher.finish() as usize;
+        let tag = (hash >> 57) as u8;
+        let mut group_index = hash & GROUP_MASK;
+        unsafe {
+            loop {
+                let group = self.control.get_unchecked_mut(group_index);
+                let mut matches = group.match_tag(tag);
+                while matches != 0 {
+                    let index = group_index * GROUP_SIZE + matches.trailing_zeros() as usize;
+                    if *self.names.get_unchecked(index) == *name {
+                        return self.entries.get_unchecked_mut(index);
+                    }
+                    matches &= matches - 1;
+                }
+                // nothing is ever removed, so a group with a free slot ends the probe sequence
+                let empty = group.match_empty();
+                if empty != 0 {
+                    let slot = empty.trailing_zeros() as usize;
+                    *group.0.get_unchecked_mut(slot) = tag;
+                    let index = group_index * GROUP_SIZE + slot;
+                    *self.names.get_unchecked_mut(index) = *name;
+                    return self.entries.get_unchecked_mut(index);
+                }
+                group_index = (group_index + 1) & GROUP_MASK;
+            }
+        }
+    }
+
+    pub fn insert_measurement(&mut self, name: StationName, measurement: i32) {
+        self.get_entry(&name).add_measurement(measurement);
+    }
+
+    pub fn merge_entry(&mut self, name: &StationName, other_entry: &StationEntry) {
+        self.get_entry(name).merge(other_entry);
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&StationName, &StationEntry)> {
+        self.control
+            .iter()
+            .flat_map(|group| group.0.iter())
+            .zip(self.names.iter().zip(self.entries.iter()))
+            .filter(|(control, _)| **control != EMPTY)
+            .map(|(_, name_and_entry)| name_and_entry)
+    }
+}
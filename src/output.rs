@@ -0,0 +1,365 @@
+use std::{
+    borrow::Cow,
+    ffi::OsString,
+    fs::{self, File},
+    io::{self, BufWriter, Write},
+    path::PathBuf,
+    str::FromStr,
+};
+
+#[cfg(feature = "arrow")]
+use crate::arrow_export;
+use crate::{my_hashmap::Aggregator, ranking::Ranking};
+
+/// `--format`, how the results are written.
+#[derive(Clone, Copy, Default)]
+pub enum Format {
+    /// `{name=min/mean/max, ...}`, the challenge's own format.
+    #[default]
+    Brace,
+    /// `[{"station":"name","min":1.0,...}, ...]`
+    Json,
+    /// `{"name":{"min":1.0,...}, ...}`
+    JsonObject,
+    /// One line per result after a header line, with the keys of nested results in separate
+    /// columns.
+    Csv,
+    Tsv,
+    /// An Arrow IPC file with the rows of `Csv`, see `arrow_export`.
+    #[cfg(feature = "arrow")]
+    Arrow,
+    /// A Parquet file with the rows of `Csv`.
+    #[cfg(feature = "arrow")]
+    Parquet,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        match text {
+            "brace" => Ok(Format::Brace),
+            "json" => Ok(Format::Json),
+            "json-object" => Ok(Format::JsonObject),
+            "csv" => Ok(Format::Csv),
+            "tsv" => Ok(Format::Tsv),
+            #[cfg(feature = "arrow")]
+            "arrow" => Ok(Format::Arrow),
+            #[cfg(feature = "arrow")]
+            "parquet" => Ok(Format::Parquet),
+            _ => Err(format!("unknown output format: {text}")),
+        }
+    }
+}
+
+impl Format {
+    /// The media type of the format, for HTTP responses.
+    pub fn content_type(self) -> &'static str {
+        match self {
+            Format::Brace => "text/plain; charset=utf-8",
+            Format::Json | Format::JsonObject => "application/json",
+            Format::Csv => "text/csv; charset=utf-8",
+            Format::Tsv => "text/tab-separated-values; charset=utf-8",
+            #[cfg(feature = "arrow")]
+            Format::Arrow => "application/vnd.apache.arrow.file",
+            #[cfg(feature = "arrow")]
+            Format::Parquet => "application/vnd.apache.parquet",
+        }
+    }
+}
+
+/// `--output`, where the results are written.
+pub enum Destination {
+    Stdout,
+    /// A file that is only replaced once the results are complete, by renaming a temporary
+    /// file next to it, so readers never see a partial file.
+    File(PathBuf),
+}
+
+impl Destination {
+    /// Calls `write_results` with a buffered writer for the destination, and commits what it
+    /// wrote unless writing failed.
+    pub fn write(
+        &self,
+        write_results: impl FnOnce(&mut dyn Write) -> io::Result<()>,
+    ) -> io::Result<()> {
+        match self {
+            Destination::Stdout => {
+                let mut out = BufWriter::new(io::stdout().lock());
+                write_results(&mut out)?;
+                out.flush()
+            }
+            Destination::File(path) => {
+                let mut temporary_name = OsString::from(".");
+                temporary_name.push(path.file_name().expect("output path is not a file"));
+                temporary_name.push(format!(".{}.tmp", std::process::id()));
+                let temporary_path = path.with_file_name(temporary_name);
+                let mut out = BufWriter::new(File::create(&temporary_path)?);
+                let result = write_results(&mut out)
+                    .and_then(|_| out.into_inner().map_err(|error| error.into_error()))
+                    .and_then(|file| file.sync_all())
+                    .and_then(|_| fs::rename(&temporary_path, path));
+                if result.is_err() {
+                    _ = fs::remove_file(&temporary_path);
+                }
+                result
+            }
+        }
+    }
+}
+
+/// A station, group or time bucket with its statistics, and the results nested in it, like the
+/// time buckets of a station.
+pub struct Row<'a, E: Clone> {
+    pub key: Cow<'a, str>,
+    /// `None` if only the nested results have statistics, owned if it was merged for the
+    /// summary, like the statistics of a group.
+    pub entry: Option<Cow<'a, E>>,
+    pub children: Vec<Row<'a, E>>,
+}
+
+impl<'a, E: Clone> Row<'a, E> {
+    pub fn new(name: &'a [u8], entry: Option<Cow<'a, E>>) -> Row<'a, E> {
+        Row {
+            // the input is not validated, so invalid bytes are shown as U+FFFD
+            key: String::from_utf8_lossy(name),
+            entry,
+            children: Vec::new(),
+        }
+    }
+}
+
+/// The sorted results of a run, written the same way whether they are per station, per group
+/// or per time bucket.
+pub struct Summary<'a, E: Aggregator> {
+    /// What the key of each nesting level is, e.g. `["station", "bucket"]`.
+    pub levels: &'a [&'a str],
+    pub rows: Vec<Row<'a, E>>,
+    pub options: &'a E::Options,
+}
+
+impl<'a, E: Aggregator> Summary<'a, E> {
+    /// Station results sorted by name, in name order, or only the ranked stations in rank
+    /// order.
+    pub fn stations(
+        mut results: Vec<(&'a [u8], &'a E)>,
+        options: &'a E::Options,
+        ranking: Option<&Ranking>,
+    ) -> Summary<'a, E> {
+        if let Some(ranking) = ranking {
+            ranking.select(&mut results, |&(name, entry)| (name, entry));
+        }
+        Summary {
+            levels: &["station"],
+            rows: results
+                .into_iter()
+                .map(|(name, entry)| Row::new(name, Some(Cow::Borrowed(entry))))
+                .collect(),
+            options,
+        }
+    }
+
+    pub fn write_to(&self, out: &mut impl Write, format: Format) -> io::Result<()> {
+        let names: Vec<String> = E::field_names(self.options)
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect();
+        match format {
+            Format::Brace => self.write_brace(out, &self.rows),
+            Format::Json => self.write_json(out, self.levels, &self.rows, &names),
+            Format::JsonObject => self.write_json_object(out, self.levels, &self.rows, &names),
+            Format::Csv | Format::Tsv => {
+                let separator = if let Format::Csv = format {
+                    b','
+                } else {
+                    b'\t'
+                };
+                let header = self
+                    .levels
+                    .iter()
+                    .copied()
+                    .chain(names.iter().map(|n| n.as_str()));
+                for (i, name) in header.enumerate() {
+                    if i > 0 {
+                        out.write_all(&[separator])?;
+                    }
+                    write_delimited(out, separator, name)?;
+                }
+                out.write_all(b"\n")?;
+                // keys of rows above the deepest level are followed by empty ones
+                for (keys, entry) in self.flat_rows() {
+                    for level in 0..self.levels.len() {
+                        if level > 0 {
+                            out.write_all(&[separator])?;
+                        }
+                        write_delimited(out, separator, keys.get(level).unwrap_or(&""))?;
+                    }
+                    for value in entry.fields(self.options) {
+                        write!(out, "{}{value}", separator as char)?;
+                    }
+                    out.write_all(b"\n")?;
+                }
+                Ok(())
+            }
+            #[cfg(feature = "arrow")]
+            Format::Arrow => arrow_export::write_ipc(self, out),
+            #[cfg(feature = "arrow")]
+            Format::Parquet => arrow_export::write_parquet(self, out),
+        }
+    }
+
+    fn write_brace(&self, out: &mut impl Write, rows: &[Row<E>]) -> io::Result<()> {
+        out.write_all(b"{")?;
+        for (i, row) in rows.iter().enumerate() {
+            if i > 0 {
+                out.write_all(b", ")?;
+            }
+            out.write_all(row.key.as_bytes())?;
+            if let Some(entry) = &row.entry {
+                out.write_all(b"=")?;
+                entry.write_result(out, self.options)?;
+            }
+            if !row.children.is_empty() {
+                out.write_all(if row.entry.is_some() { b" " } else { b"=" })?;
+                self.write_brace(out, &row.children)?;
+            }
+        }
+        out.write_all(b"}")
+    }
+
+    /// The statistics and nested results of a row as the members of a JSON object, each
+    /// preceded by a comma if `separated`.
+    fn write_json_members(
+        &self,
+        out: &mut dyn Write,
+        levels: &[&str],
+        row: &Row<E>,
+        names: &[String],
+        mut separated: bool,
+        write_children: impl FnOnce(&mut dyn Write) -> io::Result<()>,
+    ) -> io::Result<()> {
+        if let Some(entry) = &row.entry {
+            for (name, value) in names.iter().zip(entry.fields(self.options)) {
+                if separated {
+                    out.write_all(b",")?;
+                }
+                write_json_string(out, name)?;
+                write!(out, ":{value}")?;
+                separated = true;
+            }
+        }
+        if !row.children.is_empty() {
+            if separated {
+                out.write_all(b",")?;
+            }
+            write_json_string(out, &format!("{}s", levels[1]))?;
+            out.write_all(b":")?;
+            write_children(out)?;
+        }
+        Ok(())
+    }
+
+    fn write_json(
+        &self,
+        out: &mut dyn Write,
+        levels: &[&str],
+        rows: &[Row<E>],
+        names: &[String],
+    ) -> io::Result<()> {
+        out.write_all(b"[")?;
+        for (i, row) in rows.iter().enumerate() {
+            if i > 0 {
+                out.write_all(b",")?;
+            }
+            out.write_all(b"{")?;
+            write_json_string(out, levels[0])?;
+            out.write_all(b":")?;
+            write_json_string(out, &row.key)?;
+            self.write_json_members(out, levels, row, names, true, |out| {
+                self.write_json(out, &levels[1..], &row.children, names)
+            })?;
+            out.write_all(b"}")?;
+        }
+        out.write_all(b"]")
+    }
+
+    fn write_json_object(
+        &self,
+        out: &mut dyn Write,
+        levels: &[&str],
+        rows: &[Row<E>],
+        names: &[String],
+    ) -> io::Result<()> {
+        out.write_all(b"{")?;
+        for (i, row) in rows.iter().enumerate() {
+            if i > 0 {
+                out.write_all(b",")?;
+            }
+            write_json_string(out, &row.key)?;
+            out.write_all(b":{")?;
+            self.write_json_members(out, levels, row, names, false, |out| {
+                self.write_json_object(out, &levels[1..], &row.children, names)
+            })?;
+            out.write_all(b"}")?;
+        }
+        out.write_all(b"}")
+    }
+
+    /// Every row with statistics, with the keys of the rows it is nested in and its own.
+    pub fn flat_rows(&self) -> Vec<(Vec<&str>, &E)> {
+        fn flatten<'a, E: Clone>(
+            rows: &'a [Row<E>],
+            keys: &mut Vec<&'a str>,
+            flat_rows: &mut Vec<(Vec<&'a str>, &'a E)>,
+        ) {
+            for row in rows {
+                keys.push(&row.key);
+                if let Some(entry) = &row.entry {
+                    flat_rows.push((keys.clone(), &**entry));
+                }
+                flatten(&row.children, keys, flat_rows);
+                keys.pop();
+            }
+        }
+        let mut flat_rows = Vec::new();
+        flatten(&self.rows, &mut Vec::new(), &mut flat_rows);
+        flat_rows
+    }
+}
+
+fn write_json_string(out: &mut dyn Write, text: &str) -> io::Result<()> {
+    out.write_all(b"\"")?;
+    for c in text.chars() {
+        match c {
+            '"' => out.write_all(b"\\\"")?,
+            '\\' => out.write_all(b"\\\\")?,
+            '\n' => out.write_all(b"\\n")?,
+            '\r' => out.write_all(b"\\r")?,
+            '\t' => out.write_all(b"\\t")?,
+            '\0'..='\x1f' => write!(out, "\\u{:04x}", c as u32)?,
+            _ => write!(out, "{c}")?,
+        }
+    }
+    out.write_all(b"\"")
+}
+
+/// A CSV field, quoted if needed, or a TSV field with tabs, line breaks and backslashes
+/// escaped.
+fn write_delimited(out: &mut impl Write, separator: u8, text: &str) -> io::Result<()> {
+    if separator == b'\t' {
+        for c in text.bytes() {
+            match c {
+                b'\t' => out.write_all(b"\\t")?,
+                b'\n' => out.write_all(b"\\n")?,
+                b'\r' => out.write_all(b"\\r")?,
+                b'\\' => out.write_all(b"\\\\")?,
+                _ => out.write_all(&[c])?,
+            }
+        }
+        Ok(())
+    } else if text.contains([',', '"', '\n', '\r']) {
+        write!(out, "\"{}\"", text.replace('"', "\"\""))
+    } else {
+        out.write_all(text.as_bytes())
+    }
+}
@@ -0,0 +1,168 @@
+use std::io::{self, Write};
+
+use crate::{
+    histogram::Percentile,
+    my_hashmap::{Aggregator, Count},
+};
+
+const MAGIC: &[u8; 8] = b"1BRCPART";
+// 2 since sketches hold decimals instead of tenths
+const VERSION: u32 = 2;
+
+/// Which statistics the entries keep, from `--stats`, `--percentiles` and `--sketch-accuracy`.
+#[derive(Clone, PartialEq)]
+pub enum Statistics {
+    Basic,
+    Stats,
+    Percentiles(Vec<Percentile>),
+    Sketch {
+        accuracy: f64,
+        percentiles: Vec<Percentile>,
+    },
+}
+
+/// What the entries of a run or a partial summary hold, so only partial summaries of the same
+/// layout are merged.
+#[derive(Clone, PartialEq)]
+pub struct Layout {
+    pub statistics: Statistics,
+    /// `--columns`, 1 to 4.
+    pub columns: usize,
+}
+
+/// The bytes of a partial summary that are left to read.
+#[derive(Clone, Copy)]
+pub struct Input<'a>(&'a [u8]);
+
+impl<'a> Input<'a> {
+    pub(crate) fn new(bytes: &'a [u8]) -> Input<'a> {
+        Input(bytes)
+    }
+
+    /// Everything that is left, for a file that embeds a partial summary.
+    pub(crate) fn rest(self) -> &'a [u8] {
+        self.0
+    }
+
+    pub fn bytes(&mut self, len: usize) -> Result<&'a [u8], String> {
+        if self.0.len() < len {
+            return Err("truncated partial summary".to_string());
+        }
+        let (bytes, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Ok(bytes)
+    }
+
+    /// The next `N` bytes, for `from_le_bytes`.
+    pub fn array<const N: usize>(&mut self) -> Result<[u8; N], String> {
+        Ok(self.bytes(N)?.try_into().unwrap())
+    }
+
+    /// A count, which is always written with 64 bits.
+    pub fn count(&mut self) -> Result<Count, String> {
+        Ok(u64::from_le_bytes(self.array()?))
+    }
+}
+
+/// FNV-1a, to tell a corrupt or truncated file from a valid one.
+pub(crate) fn checksum(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
+
+/// Writes `results` as a partial summary: a versioned header with the layout, every name with
+/// its entry in the little endian encoding of `Aggregator::serialize`, and a checksum of it all.
+pub fn write<E: Aggregator>(
+    out: &mut dyn Write,
+    layout: &Layout,
+    results: &[(&[u8], &E)],
+) -> io::Result<()> {
+    let mut bytes = MAGIC.to_vec();
+    bytes.extend_from_slice(&VERSION.to_le_bytes());
+    let (kind, percentiles, accuracy) = match &layout.statistics {
+        Statistics::Basic => (0, &[][..], None),
+        Statistics::Stats => (1, &[][..], None),
+        Statistics::Percentiles(percentiles) => (2, &percentiles[..], None),
+        Statistics::Sketch {
+            accuracy,
+            percentiles,
+        } => (3, &percentiles[..], Some(*accuracy)),
+    };
+    bytes.extend_from_slice(&[kind, layout.columns as u8]);
+    bytes.extend_from_slice(&(percentiles.len() as u16).to_le_bytes());
+    for percentile in percentiles {
+        bytes.extend_from_slice(&percentile.hundredths().to_le_bytes());
+    }
+    if let Some(accuracy) = accuracy {
+        bytes.extend_from_slice(&accuracy.to_le_bytes());
+    }
+    bytes.extend_from_slice(&(results.len() as u32).to_le_bytes());
+    for (name, entry) in results {
+        bytes.extend_from_slice(&(name.len() as u16).to_le_bytes());
+        bytes.extend_from_slice(name);
+        entry.serialize(&mut bytes);
+    }
+    bytes.extend_from_slice(&checksum(&bytes).to_le_bytes());
+    out.write_all(&bytes)
+}
+
+/// Checks the header and checksum of a partial summary, and returns its layout and its entries
+/// for `read_entries`.
+pub fn read(bytes: &[u8]) -> Result<(Layout, Input<'_>), String> {
+    if !bytes.starts_with(MAGIC) {
+        return Err("not a partial summary".to_string());
+    }
+    let Some((contents, expected)) = bytes.split_last_chunk::<8>() else {
+        return Err("truncated partial summary".to_string());
+    };
+    if checksum(contents) != u64::from_le_bytes(*expected) {
+        return Err("corrupt partial summary, the checksum does not match".to_string());
+    }
+    let mut input = Input(&contents[MAGIC.len()..]);
+    let version = u32::from_le_bytes(input.array()?);
+    if version != VERSION {
+        return Err(format!("unsupported partial summary version {version}"));
+    }
+    let [kind, columns] = input.array()?;
+    let percentiles = (0..u16::from_le_bytes(input.array()?))
+        .map(|_| Percentile::from_hundredths(u32::from_le_bytes(input.array()?)))
+        .collect::<Result<Vec<_>, _>>()?;
+    let statistics = match kind {
+        0 => Statistics::Basic,
+        1 => Statistics::Stats,
+        2 => Statistics::Percentiles(percentiles),
+        3 => Statistics::Sketch {
+            accuracy: f64::from_le_bytes(input.array()?),
+            percentiles,
+        },
+        _ => return Err(format!("unknown statistics {kind}")),
+    };
+    let columns = columns as usize;
+    Ok((
+        Layout {
+            statistics,
+            columns,
+        },
+        input,
+    ))
+}
+
+/// The names and entries of a partial summary after `read`, in the order they were written. Names
+/// are the bytes of the input, which need not be UTF-8, like the names of a run.
+pub fn read_entries<'a, E: Aggregator>(
+    mut input: Input<'a>,
+    options: &E::Options,
+) -> Result<Vec<(&'a [u8], E)>, String> {
+    let len = u32::from_le_bytes(input.array()?);
+    let mut entries = Vec::with_capacity(len as usize);
+    for _ in 0..len {
+        let name_len = u16::from_le_bytes(input.array()?);
+        let name = input.bytes(name_len as usize)?;
+        entries.push((name, E::deserialize(&mut input, options)?));
+    }
+    if !input.0.is_empty() {
+        return Err("unexpected data after the entries".to_string());
+    }
+    Ok(entries)
+}
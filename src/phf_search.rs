@@ -0,0 +1,193 @@
+use std::{
+    arch::x86_64::_pext_u64,
+    fmt::{self, Display, Formatter},
+    hash::Hasher,
+    ops::Range,
+};
+
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+use rustc_hash::FxHasher;
+
+// shared by `my_phf` and the build script, so this module can only depend on std, rayon and
+// rustc-hash
+
+const MAX_SIZE: usize = 1 << 20;
+// keeps the 8 byte sample within the 32 bytes `MyPHFMap` already reads to verify the name
+const MAX_OFFSET: usize = 32 - 8;
+// for samples that look random, a table of `size` slots is free of collisions with a chance of
+// about e^-(pairs / size), so beyond this many pairs of names per slot the search is hopeless
+const MAX_PAIRS_PER_SLOT: usize = 8;
+
+/// How the sample of a name is turned into a key, whose remainder modulo the table size is the
+/// index of the name.
+#[derive(Clone, Copy)]
+pub enum PhfHash {
+    /// The sample itself.
+    Modulo,
+    /// `FxHasher` with `seed`.
+    FxHash { seed: usize },
+    /// The bits of the sample in `mask`, packed together by `pext`.
+    Pext { mask: u64 },
+}
+
+impl PhfHash {
+    #[inline(always)]
+    pub fn key(self, sample: u64) -> u64 {
+        match self {
+            PhfHash::Modulo => sample,
+            PhfHash::FxHash { seed } => {
+                let mut hasher = FxHasher::with_seed(seed);
+                hasher.write_u64(sample);
+                hasher.finish()
+            }
+            // BMI2 is already required by the `bzhi` of `Phf::get_name_index`
+            PhfHash::Pext { mask } => unsafe { _pext_u64(sample, mask) },
+        }
+    }
+}
+
+/// `width` bytes are sampled from each name starting at `offset`, and the key of the sample
+/// modulo `size` is the index of the name.
+#[derive(Clone, Copy)]
+pub struct PhfParameters {
+    pub offset: usize,
+    pub width: usize,
+    pub hash: PhfHash,
+    pub size: usize,
+}
+
+/// The sample offsets and widths to try, and the largest acceptable table.
+pub struct SearchSpace {
+    pub offsets: Range<usize>,
+    pub widths: Range<usize>,
+    pub max_size: usize,
+}
+
+impl Default for SearchSpace {
+    fn default() -> Self {
+        SearchSpace {
+            offsets: 0..MAX_OFFSET + 1,
+            widths: 1..9,
+            max_size: MAX_SIZE,
+        }
+    }
+}
+
+/// Reads `width` bytes of `name` starting at `offset`, without reading past its end.
+pub fn get_name_sample(name: &[u8], offset: usize, width: usize) -> u64 {
+    let mut sample = [0u8; 8];
+    let sampled = name
+        .get(offset..name.len().min(offset + width))
+        .unwrap_or_default();
+    sample[..sampled.len()].copy_from_slice(sampled);
+    u64::from_le_bytes(sample)
+}
+
+/// As a Rust expression, for generated constants.
+impl Display for PhfHash {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            PhfHash::Modulo => write!(f, "PhfHash::Modulo"),
+            PhfHash::FxHash { seed } => write!(f, "PhfHash::FxHash {{ seed: {seed} }}"),
+            PhfHash::Pext { mask } => write!(f, "PhfHash::Pext {{ mask: {mask:#018x} }}"),
+        }
+    }
+}
+
+/// Why `search` found no perfect hash function.
+#[derive(Debug)]
+pub enum SearchError {
+    /// A table of `max_size` slots is too small to be collision free for this many names.
+    TooManyNames { names: usize, max_size: usize },
+    /// No sample in the search space tells all the names apart within the size limit.
+    NotFound,
+}
+
+impl Display for SearchError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            SearchError::TooManyNames { names, max_size } => write!(
+                f,
+                "{names} names are too many for a perfect hash table of at most {max_size} slots"
+            ),
+            SearchError::NotFound => write!(f, "no perfect hash function found"),
+        }
+    }
+}
+
+/// Smallest table size in `min_size..max_size` that maps all the keys to different slots.
+fn find_size(keys: &[u64], min_size: usize, max_size: usize) -> Option<usize> {
+    (min_size..max_size)
+        .into_par_iter()
+        .map_init(
+            // a slot holds the last size it was used at, so it is never cleared
+            || vec![0u32; max_size],
+            |used, size| {
+                let collision_free = keys.iter().all(|&key| {
+                    let slot = &mut used[(key % size as u64) as usize];
+                    std::mem::replace(slot, size as u32) != size as u32
+                });
+                (size, collision_free)
+            },
+        )
+        .find_first(|&(_, collision_free)| collision_free)
+        .map(|(size, _)| size)
+}
+
+/// Searches every sample offset and width for the one that allows the smallest table, with the
+/// sample itself as the key.
+pub fn search(names: &[Vec<u8>]) -> Result<PhfParameters, SearchError> {
+    search_in(names, &SearchSpace::default(), &[PhfHash::Modulo], |_| {})
+}
+
+/// Like `search`, but only within `space`, with each of `hashes` (e.g. the seeds of a hash
+/// family), and `on_improvement` is called whenever a smaller table is found. Gives up right
+/// away if there are too many names for `space.max_size`.
+pub fn search_in(
+    names: &[Vec<u8>],
+    space: &SearchSpace,
+    hashes: &[PhfHash],
+    mut on_improvement: impl FnMut(&PhfParameters),
+) -> Result<PhfParameters, SearchError> {
+    let pairs = names.len() * names.len().saturating_sub(1) / 2;
+    if pairs > MAX_PAIRS_PER_SLOT * space.max_size {
+        return Err(SearchError::TooManyNames {
+            names: names.len(),
+            max_size: space.max_size,
+        });
+    }
+    let max_len = names.iter().map(|name| name.len()).max().unwrap_or(0);
+    let mut best: Option<PhfParameters> = None;
+    // names shorter than the offset all sample as 0, the duplicate check below rejects
+    // offsets where that happens to more than one name
+    for offset in space.offsets.start..space.offsets.end.min(max_len) {
+        for width in space.widths.clone() {
+            let samples: Vec<u64> = names
+                .iter()
+                .map(|name| get_name_sample(name, offset, width))
+                .collect();
+            for &hash in hashes {
+                let keys: Vec<u64> = samples.iter().map(|&sample| hash.key(sample)).collect();
+                let mut sorted_keys = keys.clone();
+                sorted_keys.sort_unstable();
+                if sorted_keys.windows(2).any(|pair| pair[0] == pair[1]) {
+                    // no table size can separate identical keys
+                    continue;
+                }
+                let max_size = best.map_or(space.max_size, |best| best.size);
+                // `Phf` needs at least 2 slots
+                if let Some(size) = find_size(&keys, names.len().max(2), max_size) {
+                    let parameters = PhfParameters {
+                        offset,
+                        width,
+                        hash,
+                        size,
+                    };
+                    on_improvement(&parameters);
+                    best = Some(parameters);
+                }
+            }
+        }
+    }
+    best.ok_or(SearchError::NotFound)
+}
@@ -13,10 +13,10 @@ use rustc_hash::FxHashMap;
 use memchr::memchr;
 
 struct StationEntry {
-    sum: i32,
+    sum: i64,
     min: i32,
     max: i32,
-    count: i32,
+    count: u64,
 }
 #[derive(Eq)]
 struct StationName {
@@ -165,11 +165,11 @@ fn process_chunk(chunk: &[u8]) -> FxHashMap<StationName, StationEntry> {
                 if measurement > e.max {
                     e.max = measurement;
                 }
-                e.sum += measurement;
+                e.sum += measurement as i64;
                 e.count += 1;
             })
             .or_insert(StationEntry {
-                sum: measurement,
+                sum: measurement as i64,
                 min: measurement,
                 max: measurement,
                 count: 1,
@@ -0,0 +1,101 @@
+use std::{borrow::Cow, path::PathBuf};
+
+use pyo3::{
+    buffer::PyBuffer,
+    prelude::*,
+    types::{PyBytes, PyDict, PyList},
+};
+
+use crate::{my_hashmap::StationEntry, use_phf};
+
+/// The statistics of every station in the file at `path`, aggregated with `threads` threads, or
+/// one per CPU if 0, without holding the GIL. A last line without a line break is ignored.
+///
+/// Returns a list of `{"station", "min", "mean", "max", "count"}` dicts in name order, or with
+/// `columns=True` a dict of lists with those keys, which `pandas.DataFrame` takes as is. The mean
+/// is rounded half up to one decimal, as the program writes it.
+#[pyfunction]
+#[pyo3(signature = (path, threads = 0, columns = false))]
+fn aggregate(
+    py: Python<'_>,
+    path: PathBuf,
+    threads: usize,
+    columns: bool,
+) -> PyResult<Bound<'_, PyAny>> {
+    let results = py.detach(|| use_phf::aggregate_file(&path, threads))?;
+    to_python(py, &results, columns)
+}
+
+/// `aggregate` for lines that are already in memory, in `bytes`, a `bytearray`, a `memoryview`
+/// or any other buffer of bytes. Only `bytes` are read in place, as other buffers could be
+/// changed by other threads while the GIL is released, even through a read-only view, so they
+/// are copied first.
+#[pyfunction]
+#[pyo3(signature = (data, threads = 0, columns = false))]
+fn aggregate_bytes<'py>(
+    py: Python<'py>,
+    data: &Bound<'py, PyAny>,
+    threads: usize,
+    columns: bool,
+) -> PyResult<Bound<'py, PyAny>> {
+    let lines = match data.cast::<PyBytes>() {
+        Ok(bytes) => Cow::Borrowed(bytes.as_bytes()),
+        Err(_) => Cow::Owned(PyBuffer::<u8>::get(data)?.to_vec(py)?),
+    };
+    let results = py.detach(|| use_phf::aggregate_bytes(&lines, threads))?;
+    to_python(py, &results, columns)
+}
+
+fn to_python<'py>(
+    py: Python<'py>,
+    results: &[(Box<[u8]>, StationEntry)],
+    columns: bool,
+) -> PyResult<Bound<'py, PyAny>> {
+    let rows = results.iter().map(|(name, entry)| {
+        let (min, mean, max) = entry.get_result();
+        (
+            String::from_utf8_lossy(name),
+            min.0 as f64 / 10.0,
+            mean.0 as f64 / 10.0,
+            max.0 as f64 / 10.0,
+            entry.count,
+        )
+    });
+    if columns {
+        let (mut station, mut min, mut mean, mut max, mut count) =
+            (Vec::new(), Vec::new(), Vec::new(), Vec::new(), Vec::new());
+        for row in rows {
+            station.push(row.0);
+            min.push(row.1);
+            mean.push(row.2);
+            max.push(row.3);
+            count.push(row.4);
+        }
+        let table = PyDict::new(py);
+        table.set_item("station", station)?;
+        table.set_item("min", min)?;
+        table.set_item("mean", mean)?;
+        table.set_item("max", max)?;
+        table.set_item("count", count)?;
+        Ok(table.into_any())
+    } else {
+        let records = PyList::empty(py);
+        for (station, min, mean, max, count) in rows {
+            let record = PyDict::new(py);
+            record.set_item("station", station)?;
+            record.set_item("min", min)?;
+            record.set_item("mean", mean)?;
+            record.set_item("max", max)?;
+            record.set_item("count", count)?;
+            records.append(record)?;
+        }
+        Ok(records.into_any())
+    }
+}
+
+/// Aggregation of `station;measurement` lines into per station statistics.
+#[pymodule]
+fn brc(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_function(wrap_pyfunction!(aggregate, module)?)?;
+    module.add_function(wrap_pyfunction!(aggregate_bytes, module)?)
+}
@@ -0,0 +1,73 @@
+use std::{cmp::Ordering, str::FromStr};
+
+use crate::my_hashmap::{Aggregator, StationEntry};
+
+/// `--rank-by`, which statistic stations are ranked by.
+#[derive(Clone, Copy)]
+pub enum Metric {
+    Mean,
+    Max,
+    Min,
+    Count,
+}
+
+impl FromStr for Metric {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        match text {
+            "mean" => Ok(Metric::Mean),
+            "max" => Ok(Metric::Max),
+            "min" => Ok(Metric::Min),
+            "count" => Ok(Metric::Count),
+            _ => Err(format!("unknown ranking metric: {text}")),
+        }
+    }
+}
+
+impl Metric {
+    /// Ascending order of the metric. Means are compared exactly rather than after rounding,
+    /// by cross-multiplying the sums and counts.
+    pub fn compare(self, a: &StationEntry, b: &StationEntry) -> Ordering {
+        match self {
+            Metric::Mean => {
+                let a_mean = a.sum as i128 * b.count as i128;
+                let b_mean = b.sum as i128 * a.count as i128;
+                a_mean.cmp(&b_mean)
+            }
+            Metric::Max => a.max.cmp(&b.max),
+            Metric::Min => a.min.cmp(&b.min),
+            Metric::Count => a.count.cmp(&b.count),
+        }
+    }
+}
+
+/// `--top K` or `--bottom K` with `--rank-by METRIC`: only the K highest or lowest results, in
+/// rank order instead of by name.
+pub struct Ranking {
+    pub metric: Metric,
+    pub descending: bool,
+    pub k: usize,
+}
+
+impl Ranking {
+    /// Keeps the first `k` results in rank order, with ties in name order. Only the kept
+    /// results are sorted, the rest are just partitioned off.
+    pub fn select<T, E: Aggregator>(&self, results: &mut Vec<T>, key: impl Fn(&T) -> (&[u8], &E)) {
+        let compare = |a: &T, b: &T| {
+            let ((a_name, a_entry), (b_name, b_entry)) = (key(a), key(b));
+            let order = a_entry.compare(b_entry, self.metric);
+            let order = if self.descending {
+                order.reverse()
+            } else {
+                order
+            };
+            order.then_with(|| a_name.cmp(b_name))
+        };
+        if self.k < results.len() {
+            results.select_nth_unstable_by(self.k, compare);
+            results.truncate(self.k);
+        }
+        results.sort_unstable_by(compare);
+    }
+}
@@ -0,0 +1,345 @@
+use std::{
+    collections::HashSet,
+    ffi::{OsStr, OsString},
+    fs::{self, File},
+    io::{self, BufRead, BufReader, ErrorKind, Read, Write},
+    net::TcpListener,
+    os::unix::{ffi::OsStrExt, fs::FileTypeExt, net::UnixListener},
+    path::{Path, PathBuf},
+    sync::{Mutex, mpsc},
+    thread,
+    time::Duration,
+};
+
+use crate::{
+    follow::{self, Stations},
+    my_hashmap::Aggregator,
+    my_phf::Phf,
+    output::{Format, Summary},
+    ranking::{Metric, Ranking},
+    use_phf::{
+        Fields, MARGIN, Report, Task, aggregate_chunks, map_file, padded_chunks, unmap_file,
+    },
+};
+
+// a request line and headers longer than this are cut off
+const MAX_REQUEST_SIZE: u64 = 1 << 16;
+// connections are answered by this many threads, and the next ones wait to be accepted
+const CONNECTION_THREADS: usize = 16;
+// a client that sends or reads nothing for this long is dropped, so it cannot hold a thread
+const CONNECTION_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// `--serve`, where queries are accepted.
+pub(crate) enum Address {
+    /// `host:port`, e.g. `127.0.0.1:8080`.
+    Tcp(String),
+    /// `unix:PATH`
+    Unix(PathBuf),
+}
+
+impl Address {
+    pub(crate) fn parse(text: &str) -> Address {
+        match text.strip_prefix("unix:") {
+            Some(path) => Address::Unix(path.into()),
+            None => Address::Tcp(text.to_string()),
+        }
+    }
+}
+
+/// `--serve ADDRESS`: after aggregating measurements.txt and the files in the `--watch`
+/// directory, answers HTTP queries about the results, while aggregating the files that are
+/// written or moved into the directory later. Never returns.
+///
+/// - `GET /all` for every station
+/// - `GET /station/NAME` for a single station, with the name percent-encoded
+/// - `GET /top` and `GET /bottom` for the `k` highest or lowest stations (10 by default) by
+///   `metric` (`mean` by default), e.g. `/top?metric=max&k=10`
+///
+/// Every query takes a `format` (`json` by default) of `--format`. Up to 16 connections are
+/// answered at a time, and a client that sends or reads nothing for 10 seconds is dropped.
+pub(crate) struct Serve<'a, I> {
+    /// The lines of measurements.txt.
+    pub(crate) chunks: I,
+    pub(crate) address: Address,
+    pub(crate) watch: Option<PathBuf>,
+    pub(crate) thread_count: usize,
+    pub(crate) phf: &'a Phf,
+    pub(crate) report: &'a Report,
+}
+
+impl<'a, I: Iterator<Item = &'a [u8]> + Send> Task for Serve<'a, I> {
+    fn run<E: Aggregator<Measurement: Fields>>(self, options: &E::Options) {
+        let filter = self.report.filter.as_ref();
+        let stations = Mutex::new(Stations::default());
+        let map = aggregate_chunks::<E>(self.chunks, self.phf, options, filter);
+        stations.lock().unwrap().add(map.results());
+        drop(map);
+        let add_file = |path: &Path| -> io::Result<()> {
+            let file = File::open(path)?;
+            if !file.metadata()?.is_file() {
+                return Ok(());
+            }
+            let mapped_file = map_file(&file, 0)?;
+            // the margin past the end of the file is not readable if it starts a page
+            let lines = &mapped_file[..mapped_file.len() - MARGIN];
+            let mut tail = Vec::new();
+            let chunks = padded_chunks(lines, self.thread_count, &mut tail);
+            let map = aggregate_chunks::<E>(chunks, self.phf, options, filter);
+            stations.lock().unwrap().add(map.results());
+            // the names of unknown stations point into the file until they are added
+            drop(map);
+            unsafe { unmap_file(mapped_file) };
+            Ok(())
+        };
+
+        // watching before listing the directory, so no file is missed
+        let mask = libc::IN_CLOSE_WRITE | libc::IN_MOVED_TO;
+        let watch = self.watch.as_deref().map(|dir| {
+            let events = follow::watch_directory(dir, mask)
+                .unwrap_or_else(|error| panic!("{}: {error}", dir.display()));
+            let mut files = Files {
+                dir,
+                seen: HashSet::new(),
+            };
+            let mut names: Vec<OsString> = fs::read_dir(dir)
+                .and_then(|entries| entries.map(|entry| Ok(entry?.file_name())).collect())
+                .unwrap_or_else(|error| panic!("{}: {error}", dir.display()));
+            names.sort_unstable();
+            for name in names {
+                files.add(name, add_file);
+            }
+            (files, events)
+        });
+        let listener = Listener::bind(&self.address).expect("failed to listen for queries");
+        // no capacity, so connections are only accepted while a thread is free
+        let (sender, receiver) = mpsc::sync_channel::<Box<dyn Connection>>(0);
+        let receiver = Mutex::new(receiver);
+        thread::scope(|scope| {
+            if let Some((mut files, events)) = watch {
+                scope.spawn(move || {
+                    loop {
+                        follow::wait(&[&events], -1).expect("poll failed");
+                        let names = read_names(&events).expect("failed to read file events");
+                        for name in names {
+                            files.add(name, add_file);
+                        }
+                    }
+                });
+            }
+            for _ in 0..CONNECTION_THREADS {
+                let (receiver, stations) = (&receiver, &stations);
+                scope.spawn(move || {
+                    loop {
+                        // the lock is only held until a connection is received
+                        let mut connection = receiver.lock().unwrap().recv().unwrap();
+                        // a client that goes away does not concern the server
+                        _ = handle(&mut *connection, stations, options);
+                    }
+                });
+            }
+            loop {
+                match listener.accept() {
+                    Ok(connection) => sender.send(connection).unwrap(),
+                    Err(error) => eprintln!("failed to accept a connection: {error}"),
+                }
+            }
+        });
+    }
+}
+
+/// The files in the `--watch` directory, which are aggregated in name order when the server
+/// starts, and then as they are written or moved into it. Each name is aggregated once, and
+/// names starting with `.` are skipped, so files can be written under such a name and moved into
+/// place once complete.
+struct Files<'a> {
+    dir: &'a Path,
+    seen: HashSet<OsString>,
+}
+
+impl Files<'_> {
+    fn add(&mut self, name: OsString, add_file: impl Fn(&Path) -> io::Result<()>) {
+        if name.as_bytes().starts_with(b".") || !self.seen.insert(name.clone()) {
+            return;
+        }
+        let path = self.dir.join(name);
+        if let Err(error) = add_file(&path) {
+            eprintln!("{}: {error}", path.display());
+        }
+    }
+}
+
+/// The names of the files in the inotify events that are ready.
+fn read_names(mut events: &File) -> io::Result<Vec<OsString>> {
+    // `struct inotify_event` is followed by its null padded name
+    const HEADER_SIZE: usize = 16;
+    let mut names = Vec::new();
+    let mut buffer = [0; 4096];
+    loop {
+        let mut rest = match events.read(&mut buffer) {
+            Ok(len) => &buffer[..len],
+            Err(error) if error.kind() == ErrorKind::WouldBlock => return Ok(names),
+            Err(error) if error.kind() == ErrorKind::Interrupted => continue,
+            Err(error) => return Err(error),
+        };
+        while rest.len() >= HEADER_SIZE {
+            let name_len = u32::from_ne_bytes(rest[12..HEADER_SIZE].try_into().unwrap()) as usize;
+            let name = &rest[HEADER_SIZE..HEADER_SIZE + name_len];
+            let name = name.split(|&c| c == 0).next().unwrap();
+            names.push(OsStr::from_bytes(name).to_os_string());
+            rest = &rest[HEADER_SIZE + name_len..];
+        }
+    }
+}
+
+trait Connection: Read + Write + Send {}
+
+impl<T: Read + Write + Send> Connection for T {}
+
+enum Listener {
+    Tcp(TcpListener),
+    Unix(UnixListener),
+}
+
+impl Listener {
+    fn bind(address: &Address) -> io::Result<Listener> {
+        match address {
+            Address::Tcp(address) => Ok(Listener::Tcp(TcpListener::bind(address)?)),
+            Address::Unix(path) => {
+                // left behind by a previous server
+                if fs::symlink_metadata(path).is_ok_and(|metadata| metadata.file_type().is_socket())
+                {
+                    fs::remove_file(path)?;
+                }
+                Ok(Listener::Unix(UnixListener::bind(path)?))
+            }
+        }
+    }
+
+    fn accept(&self) -> io::Result<Box<dyn Connection>> {
+        Ok(match self {
+            Listener::Tcp(listener) => {
+                let (stream, _) = listener.accept()?;
+                stream.set_read_timeout(Some(CONNECTION_TIMEOUT))?;
+                stream.set_write_timeout(Some(CONNECTION_TIMEOUT))?;
+                Box::new(stream)
+            }
+            Listener::Unix(listener) => {
+                let (stream, _) = listener.accept()?;
+                stream.set_read_timeout(Some(CONNECTION_TIMEOUT))?;
+                stream.set_write_timeout(Some(CONNECTION_TIMEOUT))?;
+                Box::new(stream)
+            }
+        })
+    }
+}
+
+/// Answers a single request, and closes the connection.
+fn handle<E: Aggregator>(
+    connection: &mut dyn Connection,
+    stations: &Mutex<Stations<E>>,
+    options: &E::Options,
+) -> io::Result<()> {
+    let mut request_line = String::new();
+    {
+        let mut reader = BufReader::new((&mut *connection).take(MAX_REQUEST_SIZE));
+        reader.read_line(&mut request_line)?;
+        // the headers do not matter, but are read so the client gets the whole response
+        let mut header = String::new();
+        while reader.read_line(&mut header)? > 0 && !header.trim_end().is_empty() {
+            header.clear();
+        }
+    }
+    let response = match request_line.trim_end().split(' ').collect::<Vec<_>>()[..] {
+        ["GET", target, _] => respond(target, stations, options),
+        _ => Err((405, "only GET requests are supported".to_string())),
+    };
+    let (status, content_type, body) = match response {
+        Ok((format, body)) => (200, format.content_type(), body),
+        Err((status, message)) => (status, "text/plain; charset=utf-8", message.into_bytes()),
+    };
+    let reason = match status {
+        200 => "OK",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        _ => "Internal Server Error",
+    };
+    write!(
+        connection,
+        "HTTP/1.1 {status} {reason}\r\nContent-Type: {content_type}\r\n\
+         Content-Length: {}\r\nConnection: close\r\n\r\n",
+        body.len()
+    )?;
+    connection.write_all(&body)?;
+    connection.flush()
+}
+
+/// The body of the response to `target`, or its status and an error message.
+fn respond<E: Aggregator>(
+    target: &str,
+    stations: &Mutex<Stations<E>>,
+    options: &E::Options,
+) -> Result<(Format, Vec<u8>), (u16, String)> {
+    let bad_request = |message: String| (400, message);
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let mut format = Format::Json;
+    let mut metric = Metric::Mean;
+    let mut k = 10;
+    for parameter in query.split('&').filter(|parameter| !parameter.is_empty()) {
+        let (key, value) = parameter.split_once('=').unwrap_or((parameter, ""));
+        let value = percent_decode(value).ok_or_else(|| bad_request(format!("invalid {key}")))?;
+        match key {
+            "format" => format = value.parse().map_err(bad_request)?,
+            "metric" => metric = value.parse().map_err(bad_request)?,
+            "k" => {
+                k = value
+                    .parse()
+                    .map_err(|_| bad_request(format!("invalid k: {value}")))?
+            }
+            _ => return Err(bad_request(format!("unknown parameter: {key}"))),
+        }
+    }
+    let stations = stations.lock().unwrap();
+    let mut results = stations.results();
+    let ranking = match path {
+        "/all" => None,
+        "/top" | "/bottom" => Some(Ranking {
+            metric,
+            descending: path == "/top",
+            k,
+        }),
+        _ => {
+            let not_found = || (404, format!("not found: {path}"));
+            let name = path.strip_prefix("/station/").ok_or_else(not_found)?;
+            let name = percent_decode(name).ok_or_else(not_found)?;
+            results.retain(|&(station, _)| station == name.as_bytes());
+            if results.is_empty() {
+                return Err(not_found());
+            }
+            None
+        }
+    };
+    let mut body = Vec::new();
+    Summary::stations(results, options, ranking.as_ref())
+        .write_to(&mut body, format)
+        .map_err(|error| (500, error.to_string()))?;
+    Ok((format, body))
+}
+
+/// Decodes the `%XX` escapes of a URL path or query value, or `None` if they are invalid or do
+/// not decode to UTF-8.
+fn percent_decode(text: &str) -> Option<String> {
+    let mut bytes = Vec::with_capacity(text.len());
+    let mut rest = text.as_bytes();
+    while let Some((&c, after)) = rest.split_first() {
+        if c == b'%' {
+            let hex = std::str::from_utf8(after.get(..2)?).ok()?;
+            bytes.push(u8::from_str_radix(hex, 16).ok()?);
+            rest = &after[2..];
+        } else {
+            bytes.push(c);
+            rest = after;
+        }
+    }
+    String::from_utf8(bytes).ok()
+}
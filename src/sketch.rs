@@ -0,0 +1,293 @@
+use std::{
+    cmp::Ordering,
+    io::{self, Write},
+};
+
+use crate::{
+    histogram::Percentile,
+    my_hashmap::{Aggregator, Count, Decimal, Unit, Value},
+    partial::Input,
+    ranking::Metric,
+};
+
+/// `--sketch-accuracy`, the relative error of the reported percentiles, and which to report.
+pub struct SketchOptions {
+    pub percentiles: Vec<Percentile>,
+    // `1 / ln(gamma)` with `gamma = (1 + accuracy) / (1 - accuracy)`, so bucket `i` holds the
+    // magnitudes in `(gamma^(i - 1), gamma^i]`
+    multiplier: f64,
+}
+
+impl SketchOptions {
+    /// # Panics
+    /// If the accuracy is not in `(0, 1)`.
+    pub fn new(accuracy: f64, percentiles: Vec<Percentile>) -> SketchOptions {
+        assert!(
+            accuracy > 0.0 && accuracy < 1.0,
+            "sketch accuracy must be between 0 and 1"
+        );
+        let gamma = (1.0 + accuracy) / (1.0 - accuracy);
+        SketchOptions {
+            percentiles,
+            multiplier: 1.0 / gamma.ln(),
+        }
+    }
+}
+
+/// Contiguous bucket counts starting at bucket `offset`, grown in either direction as needed.
+#[derive(Clone, Default)]
+struct Buckets {
+    offset: i32,
+    counts: Vec<Count>,
+}
+
+impl Buckets {
+    fn add(&mut self, index: i32, count: Count) {
+        if self.counts.is_empty() {
+            self.offset = index;
+        } else if index < self.offset {
+            let missing = (self.offset - index) as usize;
+            self.counts.splice(0..0, std::iter::repeat_n(0, missing));
+            self.offset = index;
+        }
+        let position = (index - self.offset) as usize;
+        if position >= self.counts.len() {
+            self.counts.resize(position + 1, 0);
+        }
+        self.counts[position] += count;
+    }
+
+    fn merge(&mut self, other: &Buckets) {
+        for (position, &count) in other.counts.iter().enumerate() {
+            if count != 0 {
+                self.add(other.offset + position as i32, count);
+            }
+        }
+    }
+
+    fn serialize(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.offset.to_le_bytes());
+        out.extend_from_slice(&(self.counts.len() as u32).to_le_bytes());
+        for &count in &self.counts {
+            out.extend_from_slice(&count.to_le_bytes());
+        }
+    }
+
+    fn deserialize(input: &mut Input) -> Result<Buckets, String> {
+        let offset = i32::from_le_bytes(input.array()?);
+        let len = u32::from_le_bytes(input.array()?);
+        let counts = (0..len).map(|_| input.count()).collect::<Result<_, _>>()?;
+        Ok(Buckets { offset, counts })
+    }
+
+    fn iter(&self) -> impl DoubleEndedIterator<Item = (i32, Count)> {
+        let end = self.offset + self.counts.len() as i32;
+        (self.offset..end).zip(self.counts.iter().copied())
+    }
+}
+
+/// DDSketch: logarithmically sized buckets for either sign, so the number of buckets only grows
+/// with the log of the range of the measurements.
+#[derive(Clone, Default)]
+struct Sketch {
+    negative: Buckets,
+    zero: Count,
+    positive: Buckets,
+}
+
+/// A DDSketch of `Decimal` measurements of any magnitude and precision, for percentiles within a
+/// relative error where a bucket for every possible value like `HistogramEntry` is not an
+/// option, along with the count, mean, min and max. The sketch is only allocated once the
+/// station has a measurement.
+#[derive(Clone)]
+pub struct SketchEntry {
+    pub count: Count,
+    pub sum: f64,
+    pub min: f64,
+    pub max: f64,
+    /// The most decimals of any measurement, which every statistic is printed with.
+    pub decimals: u8,
+    multiplier: f64,
+    sketch: Option<Box<Sketch>>,
+}
+
+impl Aggregator for SketchEntry {
+    type Options = SketchOptions;
+    type Measurement = Decimal;
+
+    fn empty(options: &SketchOptions) -> SketchEntry {
+        SketchEntry {
+            count: 0,
+            sum: 0.0,
+            min: f64::INFINITY,
+            max: f64::NEG_INFINITY,
+            decimals: 0,
+            multiplier: options.multiplier,
+            sketch: None,
+        }
+    }
+
+    fn add_measurement(&mut self, measurement: Decimal) {
+        let value = measurement.value;
+        self.count += 1;
+        self.sum += value;
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+        self.decimals = self.decimals.max(measurement.decimals);
+        let sketch = self.sketch.get_or_insert_default();
+        if value > 0.0 {
+            sketch.positive.add(get_index(self.multiplier, value), 1);
+        } else if value < 0.0 {
+            sketch.negative.add(get_index(self.multiplier, value), 1);
+        } else {
+            sketch.zero += 1;
+        }
+    }
+
+    fn merge(&mut self, other: &SketchEntry) {
+        self.count += other.count;
+        self.sum += other.sum;
+        self.min = self.min.min(other.min);
+        self.max = self.max.max(other.max);
+        self.decimals = self.decimals.max(other.decimals);
+        let Some(other_sketch) = &other.sketch else {
+            return;
+        };
+        let sketch = self.sketch.get_or_insert_default();
+        sketch.negative.merge(&other_sketch.negative);
+        sketch.zero += other_sketch.zero;
+        sketch.positive.merge(&other_sketch.positive);
+    }
+
+    fn count(&self) -> Count {
+        self.count
+    }
+
+    fn compare(&self, other: &SketchEntry, metric: Metric) -> Ordering {
+        match metric {
+            Metric::Mean => self.get_mean().total_cmp(&other.get_mean()),
+            Metric::Max => self.max.total_cmp(&other.max),
+            Metric::Min => self.min.total_cmp(&other.min),
+            Metric::Count => self.count.cmp(&other.count),
+        }
+    }
+
+    fn write_result(&self, out: &mut impl Write, options: &SketchOptions) -> io::Result<()> {
+        let values = self.fields(options);
+        let (statistics, percentiles) = values.split_at(4);
+        write!(out, "{}/{}/{}", statistics[0], statistics[1], statistics[2])?;
+        for percentile in percentiles {
+            write!(out, "/{percentile}")?;
+        }
+        Ok(())
+    }
+
+    fn field_names(options: &SketchOptions) -> Vec<(String, Unit)> {
+        let mut names: Vec<(String, Unit)> = [
+            ("min", Unit::Decimal),
+            ("mean", Unit::Decimal),
+            ("max", Unit::Decimal),
+            ("count", Unit::Count),
+        ]
+        .map(|(name, unit)| (name.to_string(), unit))
+        .to_vec();
+        let percentiles = options.percentiles.iter();
+        names.extend(percentiles.map(|p| (format!("p{p}"), Unit::Decimal)));
+        names
+    }
+
+    fn serialize(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.count.to_le_bytes());
+        for value in [self.sum, self.min, self.max] {
+            out.extend_from_slice(&value.to_le_bytes());
+        }
+        out.push(self.decimals);
+        let Some(sketch) = &self.sketch else {
+            out.push(0);
+            return;
+        };
+        out.push(1);
+        sketch.negative.serialize(out);
+        out.extend_from_slice(&sketch.zero.to_le_bytes());
+        sketch.positive.serialize(out);
+    }
+
+    /// The accuracy is part of the partial summary's layout, so the buckets match `options`.
+    fn deserialize(input: &mut Input, options: &SketchOptions) -> Result<SketchEntry, String> {
+        let mut entry = SketchEntry::empty(options);
+        entry.count = input.count()?;
+        entry.sum = f64::from_le_bytes(input.array()?);
+        entry.min = f64::from_le_bytes(input.array()?);
+        entry.max = f64::from_le_bytes(input.array()?);
+        [entry.decimals] = input.array()?;
+        if input.array::<1>()? == [1] {
+            entry.sketch = Some(Box::new(Sketch {
+                negative: Buckets::deserialize(input)?,
+                zero: input.count()?,
+                positive: Buckets::deserialize(input)?,
+            }));
+        }
+        Ok(entry)
+    }
+
+    fn fields(&self, options: &SketchOptions) -> Vec<Value> {
+        let decimal = |value| {
+            Value::Decimal(Decimal {
+                value,
+                decimals: self.decimals,
+            })
+        };
+        let mut fields = vec![
+            decimal(self.min),
+            decimal(self.get_mean()),
+            decimal(self.max),
+            Value::Count(self.count),
+        ];
+        let percentiles = options.percentiles.iter();
+        fields.extend(percentiles.map(|&p| decimal(self.get_percentile(p))));
+        fields
+    }
+}
+
+/// The bucket of a measurement's magnitude, meaningless for 0 which has its own count.
+fn get_index(multiplier: f64, value: f64) -> i32 {
+    (value.abs().ln() * multiplier).ceil() as i32
+}
+
+impl SketchEntry {
+    pub fn get_mean(&self) -> f64 {
+        self.sum / self.count as f64
+    }
+
+    /// The middle of bucket `index`, within the relative accuracy of every magnitude in it.
+    fn get_magnitude(&self, index: i32) -> f64 {
+        let gamma = (1.0 / self.multiplier).exp();
+        2.0 * gamma.powi(index) / (gamma + 1.0)
+    }
+
+    /// Nearest rank percentile like `HistogramEntry::get_percentile`, within the sketch's
+    /// relative accuracy and clamped to the min and max. The station must have a measurement.
+    pub fn get_percentile(&self, percentile: Percentile) -> f64 {
+        let sketch = self.sketch.as_deref().expect("no measurements");
+        let rank = percentile.rank(self.count);
+        let mut seen = 0;
+        // the most negative measurements are in the highest negative buckets
+        let negative = sketch
+            .negative
+            .iter()
+            .rev()
+            .map(|(i, c)| (-self.get_magnitude(i), c));
+        let zero = std::iter::once((0.0, sketch.zero));
+        let positive = sketch
+            .positive
+            .iter()
+            .map(|(i, c)| (self.get_magnitude(i), c));
+        for (value, count) in negative.chain(zero).chain(positive) {
+            seen += count as u128;
+            if seen >= rank {
+                return value.clamp(self.min, self.max);
+            }
+        }
+        unreachable!("the sketch holds every measurement")
+    }
+}
@@ -0,0 +1,88 @@
+use std::{cmp::Ordering, str::FromStr};
+
+use deunicode::deunicode;
+
+use crate::{my_hashmap::Aggregator, output::Row, ranking::Metric};
+
+/// What results are compared by.
+#[derive(Clone, Copy)]
+pub enum SortKey {
+    /// The raw bytes of the names, how results are sorted without `--sort`.
+    Bytes,
+    /// Unicode scalar values, the same as `Bytes` for names that are valid UTF-8, unlike the
+    /// UTF-16 order of the reference implementation.
+    Codepoint,
+    /// Lowercase names, with names that only differ in case in byte order.
+    CaseInsensitive,
+    /// A simple root collation: names are compared without accents and case first, e.g.
+    /// `Abéché` sorts with `Abeche` and `İzmir` with `Izmir`, then with accents but without
+    /// case, then by bytes.
+    Locale,
+    /// A statistic, with ties in byte order of the names in either direction, like `--top`.
+    Metric(Metric),
+}
+
+/// `--sort KEY[:desc]`, applied to every level of the results when they are written, after
+/// `--top` or `--bottom` picked them.
+pub struct SortOrder {
+    pub key: SortKey,
+    pub descending: bool,
+}
+
+impl FromStr for SortOrder {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let (key, descending) = match text.strip_suffix(":desc") {
+            Some(key) => (key, true),
+            None => (text.strip_suffix(":asc").unwrap_or(text), false),
+        };
+        let key = match key {
+            "bytes" => SortKey::Bytes,
+            "codepoint" => SortKey::Codepoint,
+            "case-insensitive" => SortKey::CaseInsensitive,
+            "locale" => SortKey::Locale,
+            _ => SortKey::Metric(
+                key.parse()
+                    .map_err(|_| format!("unknown sort order: {key}"))?,
+            ),
+        };
+        Ok(SortOrder { key, descending })
+    }
+}
+
+impl SortOrder {
+    /// Sorts the rows and the rows nested in them. Rows without statistics, like the stations
+    /// of time buckets, are only compared by name.
+    pub fn sort<E: Aggregator>(&self, rows: &mut [Row<E>]) {
+        match self.key {
+            SortKey::Bytes => rows.sort_by(|a, b| a.key.as_bytes().cmp(b.key.as_bytes())),
+            SortKey::Codepoint => rows.sort_by(|a, b| a.key.chars().cmp(b.key.chars())),
+            SortKey::CaseInsensitive => {
+                rows.sort_by_cached_key(|row| (row.key.to_lowercase(), row.key.to_string()))
+            }
+            SortKey::Locale => rows.sort_by_cached_key(|row| {
+                let lowercase = row.key.to_lowercase();
+                (deunicode(&lowercase), lowercase, row.key.to_string())
+            }),
+            SortKey::Metric(metric) => rows.sort_by(|a, b| {
+                let order = match (&a.entry, &b.entry) {
+                    (Some(a), Some(b)) => a.compare(b, metric),
+                    _ => Ordering::Equal,
+                };
+                let order = if self.descending {
+                    order.reverse()
+                } else {
+                    order
+                };
+                order.then_with(|| a.key.cmp(&b.key))
+            }),
+        }
+        if self.descending && !matches!(self.key, SortKey::Metric(_)) {
+            rows.reverse();
+        }
+        for row in rows {
+            self.sort(&mut row.children);
+        }
+    }
+}
@@ -0,0 +1,202 @@
+use std::{
+    borrow::Cow,
+    io::{self, Write},
+    str::FromStr,
+};
+
+use crate::{
+    my_hashmap::{Aggregator, MapKey, MyHashMap, StationName},
+    output::{Row, Summary},
+};
+
+const SECONDS_PER_HOUR: i64 = 60 * 60;
+const SECONDS_PER_DAY: i64 = 24 * SECONDS_PER_HOUR;
+
+/// `--time-buckets`, how measurements of `station;timestamp;value` lines are grouped in time.
+#[derive(Clone, Copy)]
+pub enum BucketWidth {
+    Hour,
+    Day,
+    Month,
+}
+
+impl FromStr for BucketWidth {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        match text {
+            "hour" => Ok(BucketWidth::Hour),
+            "day" => Ok(BucketWidth::Day),
+            "month" => Ok(BucketWidth::Month),
+            _ => Err(format!("unknown time bucket width: {text}")),
+        }
+    }
+}
+
+impl BucketWidth {
+    /// Hours, days or months since the Unix epoch.
+    pub fn get_bucket(self, seconds: i64) -> i64 {
+        match self {
+            BucketWidth::Hour => seconds.div_euclid(SECONDS_PER_HOUR),
+            BucketWidth::Day => seconds.div_euclid(SECONDS_PER_DAY),
+            BucketWidth::Month => {
+                let (year, month, _) = civil_from_days(seconds.div_euclid(SECONDS_PER_DAY));
+                year * 12 + month - 1
+            }
+        }
+    }
+
+    /// The start of the bucket as a truncated ISO-8601 date, e.g. `2024-03-05T12` for an hour.
+    fn write_label(self, out: &mut impl Write, bucket: i64) -> io::Result<()> {
+        match self {
+            BucketWidth::Hour => {
+                let (year, month, day) = civil_from_days(bucket.div_euclid(24));
+                let hour = bucket.rem_euclid(24);
+                write!(out, "{year:04}-{month:02}-{day:02}T{hour:02}")
+            }
+            BucketWidth::Day => {
+                let (year, month, day) = civil_from_days(bucket);
+                write!(out, "{year:04}-{month:02}-{day:02}")
+            }
+            BucketWidth::Month => {
+                let (year, month) = (bucket.div_euclid(12), bucket.rem_euclid(12) + 1);
+                write!(out, "{year:04}-{month:02}")
+            }
+        }
+    }
+}
+
+/// Days since the Unix epoch of a proleptic Gregorian date.
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// The inverse of `days_from_civil`.
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    (year_of_era + era * 400 + (month <= 2) as i64, month, day)
+}
+
+fn parse_digits(text: &[u8]) -> i64 {
+    assert!(
+        !text.is_empty() && text.iter().all(u8::is_ascii_digit),
+        "invalid timestamp"
+    );
+    text.iter()
+        .fold(0, |n, digit| n * 10 + (digit - b'0') as i64)
+}
+
+/// Seconds since the Unix epoch, from either epoch seconds (fractions are ignored) or an
+/// ISO-8601 date and time, `YYYY-MM-DD[(T| )hh[:mm[:ss[.fff]]]][Z|(+|-)hh[:mm]]`.
+pub fn parse_timestamp(text: &[u8]) -> i64 {
+    if text.get(4) != Some(&b'-') {
+        let (negative, text) = match text.strip_prefix(b"-") {
+            Some(text) => (true, text),
+            None => (false, text),
+        };
+        let whole = text.split(|&c| c == b'.').next().unwrap();
+        let seconds = parse_digits(whole);
+        return if negative { -seconds } else { seconds };
+    }
+    let field =
+        |range: std::ops::Range<usize>| parse_digits(text.get(range).expect("invalid timestamp"));
+    let mut seconds = days_from_civil(field(0..4), field(5..7), field(8..10)) * SECONDS_PER_DAY;
+    let mut position = 10;
+    // hours, minutes and seconds, each optional once the previous one is missing
+    for (separators, unit) in [(b"T ", SECONDS_PER_HOUR), (b"::", 60), (b"::", 1)] {
+        if !text.get(position).is_some_and(|c| separators.contains(c)) {
+            break;
+        }
+        seconds += field(position + 1..position + 3) * unit;
+        position += 3;
+    }
+    if text.get(position) == Some(&b'.') {
+        position += 1;
+        while text.get(position).is_some_and(u8::is_ascii_digit) {
+            position += 1;
+        }
+    }
+    match text.get(position) {
+        None | Some(b'Z') => {}
+        Some(&sign @ (b'+' | b'-')) => {
+            let mut offset = field(position + 1..position + 3) * SECONDS_PER_HOUR;
+            let minutes = position + 3 + (text.get(position + 3) == Some(&b':')) as usize;
+            if text.len() > minutes {
+                offset += field(minutes..minutes + 2) * 60;
+            }
+            seconds -= if sign == b'+' { offset } else { -offset };
+        }
+        Some(_) => panic!("invalid timestamp"),
+    }
+    seconds
+}
+
+/// The `MyHashMap` key of a station's measurements in one time bucket.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TimedName {
+    pub name: StationName,
+    pub bucket: i64,
+}
+
+impl MapKey for TimedName {
+    const FREE: TimedName = TimedName {
+        name: StationName::FREE,
+        bucket: 0,
+    };
+
+    fn is_free(&self) -> bool {
+        self.name.is_free()
+    }
+}
+
+/// Every station with its time buckets, sorted by station and then by time.
+pub fn summary<'a, E: Aggregator>(
+    map: &'a MyHashMap<E, TimedName>,
+    width: BucketWidth,
+    options: &'a E::Options,
+) -> Summary<'a, E> {
+    let mut results: Vec<(&[u8], i64, &E)> = map
+        .iter()
+        .map(|(key, entry)| (key.name.as_bytes(), key.bucket, entry))
+        .collect();
+    results.sort_unstable_by(|r1, r2| (r1.0, r1.1).cmp(&(r2.0, r2.1)));
+    let mut rows: Vec<Row<E>> = Vec::new();
+    // the row keys are lossy, so the buckets are grouped by the raw name
+    let mut previous_name = None;
+    for (station_name, bucket, entry) in results {
+        let mut label = Vec::new();
+        let _ = width.write_label(&mut label, bucket);
+        let bucket_row = Row {
+            key: Cow::Owned(String::from_utf8(label).unwrap()),
+            entry: Some(Cow::Borrowed(entry)),
+            children: Vec::new(),
+        };
+        if previous_name != Some(station_name) {
+            rows.push(Row::new(station_name, None));
+            previous_name = Some(station_name);
+        }
+        rows.last_mut().unwrap().children.push(bucket_row);
+    }
+    Summary {
+        levels: &["station", "bucket"],
+        rows,
+        options,
+    }
+}
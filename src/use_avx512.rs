@@ -17,10 +17,10 @@ use memchr::memchr;
 const MARGIN: usize = 32;
 
 struct StationEntry {
-    sum: i32,
+    sum: i64,
     min: i32,
     max: i32,
-    count: i32,
+    count: u64,
 }
 #[derive(Eq)]
 struct StationName {
@@ -144,11 +144,11 @@ fn process_chunk(chunk: &[u8]) -> FxHashMap<StationName, StationEntry> {
                 if measurement > e.max {
                     e.max = measurement;
                 }
-                e.sum += measurement;
+                e.sum += measurement as i64;
                 e.count += 1;
             })
             .or_insert(StationEntry {
-                sum: measurement,
+                sum: measurement as i64,
                 min: measurement,
                 max: measurement,
                 count: 1,
@@ -1,5 +1,13 @@
-use std::io::{PipeWriter, Write};
-use std::{fs::File, io::Error, os::fd::AsRawFd, slice::from_raw_parts};
+use std::io::{ErrorKind, PipeWriter, Write};
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    io::Error,
+    os::fd::AsRawFd,
+    path::{Path, PathBuf},
+    slice::from_raw_parts,
+    time::Duration,
+};
 
 use rayon::iter::{ParallelBridge, ParallelIterator};
 
@@ -9,14 +17,32 @@ use std::arch::x86_64::{
     _mm256_movemask_epi8, _mm256_set1_epi8, _pext_u32,
 };
 
-use memchr::memrchr;
-
-#[allow(unused_imports)]
-use memchr::memchr;
+use memchr::{memchr, memrchr};
+use rustc_hash::FxHashSet;
 
-use crate::my_phf::MyPHFMap;
+use crate::{
+    checkpoint::{self, Checkpoint, Position},
+    columns::Columns,
+    filter::{Filter, Selection, parse_bound},
+    follow::{self, Follow},
+    group_by::GroupBy,
+    histogram::{HistogramEntry, Percentile},
+    my_hashmap::{Aggregator, Decimal, MyHashMap, StationEntry, StationName, StatsEntry},
+    my_phf::{MyPHFMap, Phf, read_names_file},
+    output::{Destination, Format, Summary},
+    partial::{self, Layout, Statistics},
+    ranking::{Metric, Ranking},
+    server::{Address, Serve},
+    sketch::{SketchEntry, SketchOptions},
+    sort_order::SortOrder,
+    time_buckets::{self, BucketWidth, TimedName, parse_timestamp},
+};
 
-const MARGIN: usize = 32;
+pub(crate) const MARGIN: usize = 32;
+// small files are split into fewer chunks, so every chunk boundary can find a line break
+const MIN_CHUNK_SIZE: usize = 1 << 16;
+// names seen in this prefix of the file are used to build the PHF in `--sample-names` mode
+const SAMPLE_SIZE: usize = 1 << 24;
 
 fn parse_measurement(text: &[u8]) -> i32 {
     static LUT: [i16; 1 << 16] = {
@@ -43,8 +69,45 @@ fn parse_measurement(text: &[u8]) -> i32 {
     if negative { -abs_val } else { abs_val }
 }
 
-fn map_file(file: &File) -> Result<&[u8], Error> {
-    let mapped_length = file.metadata().unwrap().len() as usize + MARGIN;
+/// A decimal like `-1234.5678`, with any number of digits before and after the point.
+fn parse_decimal(text: &[u8]) -> Decimal {
+    let digits = text.strip_prefix(b"-").unwrap_or(text);
+    let (whole, fraction) = match memchr(b'.', digits) {
+        Some(point) => (&digits[..point], &digits[point + 1..]),
+        None => (digits, &[][..]),
+    };
+    let is_digits = |part: &[u8]| part.iter().all(u8::is_ascii_digit);
+    assert!(
+        !(whole.is_empty() && fraction.is_empty()) && is_digits(whole) && is_digits(fraction),
+        "invalid measurement: {}",
+        String::from_utf8_lossy(text)
+    );
+    let decimals = u8::try_from(fraction.len()).expect("too many decimals");
+    let mantissa = whole.iter().chain(fraction).try_fold(0u64, |mantissa, &c| {
+        mantissa.checked_mul(10)?.checked_add((c - b'0') as u64)
+    });
+    let value = match mantissa {
+        // both operands are exact, so the division is correctly rounded
+        Some(mantissa) if mantissa < 1 << 53 && decimals <= 22 => {
+            mantissa as f64 / 10f64.powi(decimals as i32)
+        }
+        _ => std::str::from_utf8(digits).unwrap().parse().unwrap(),
+    };
+    Decimal {
+        value: if digits.len() < text.len() {
+            -value
+        } else {
+            value
+        },
+        decimals,
+    }
+}
+
+/// Maps the file from `offset` on, which does not have to be page aligned.
+pub(crate) fn map_file(file: &File, offset: u64) -> Result<&[u8], Error> {
+    let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) } as u64;
+    let page_offset = offset - offset % page_size;
+    let mapped_length = (file.metadata().unwrap().len() - page_offset) as usize + MARGIN;
     match unsafe {
         libc::mmap(
             std::ptr::null_mut(),
@@ -52,95 +115,814 @@ fn map_file(file: &File) -> Result<&[u8], Error> {
             libc::PROT_READ,
             libc::MAP_SHARED,
             file.as_raw_fd(),
-            0,
+            page_offset as libc::off_t,
         )
     } {
         libc::MAP_FAILED => Err(Error::last_os_error()),
         ptr => {
             unsafe { libc::madvise(ptr, mapped_length, libc::MADV_SEQUENTIAL) };
-            Ok(unsafe { from_raw_parts(ptr as *const u8, mapped_length) })
+            let mapped = unsafe { from_raw_parts(ptr as *const u8, mapped_length) };
+            Ok(&mapped[(offset - page_offset) as usize..])
         }
     }
 }
 
+/// Unmaps a file that is not needed anymore, for processes that keep mapping new files.
+///
+/// # Safety
+/// `mapped_file` must be the whole result of `map_file` from offset 0, and must not be used
+/// afterwards.
+pub(crate) unsafe fn unmap_file(mapped_file: &[u8]) {
+    unsafe { libc::munmap(mapped_file.as_ptr() as *mut libc::c_void, mapped_file.len()) };
+}
+
+/// The lines up to the last line break, without a last line that is still being appended.
+fn complete_lines(lines: &[u8]) -> &[u8] {
+    &lines[..memrchr(b'\n', lines).map_or(0, |end| end + 1)]
+}
+
+/// Splits the first line into the station name and everything after the `;`, and returns the
+/// rest of the text along with them.
 #[cfg(target_feature = "avx2")]
 #[target_feature(enable = "avx2")]
-fn read_line(text: &[u8]) -> (&[u8], &[u8], i32) {
+fn split_line(text: &[u8]) -> (&[u8], &[u8], &[u8]) {
     let separator: __m256i = _mm256_set1_epi8(b';' as i8);
     let line_break: __m256i = _mm256_set1_epi8(b'\n' as i8);
     let line: __m256i = unsafe { _mm256_loadu_si256(text.as_ptr() as *const __m256i) };
-    let separator_mask = _mm256_movemask_epi8(_mm256_cmpeq_epi8(line, separator));
     let line_break_mask = _mm256_movemask_epi8(_mm256_cmpeq_epi8(line, line_break));
+    if line_break_mask == 0 {
+        return split_long_line(text);
+    }
+    let separator_mask = _mm256_movemask_epi8(_mm256_cmpeq_epi8(line, separator));
     let separator_pos = separator_mask.trailing_zeros() as usize;
     let line_break_pos = line_break_mask.trailing_zeros() as usize;
     unsafe {
         (
             text.get_unchecked(line_break_pos + 1..),
             text.get_unchecked(..separator_pos),
-            parse_measurement(&text[separator_pos + 1..line_break_pos]),
+            text.get_unchecked(separator_pos + 1..line_break_pos),
         )
     }
 }
 
 #[cfg(not(target_feature = "avx2"))]
-fn read_line(mut text: &[u8]) -> (&[u8], StationName, i32) {
-    let station_name_slice: &[u8];
+fn split_line(text: &[u8]) -> (&[u8], &[u8], &[u8]) {
+    split_long_line(text)
+}
+
+/// Handles lines that do not fit in a single vector.
+#[cold]
+fn split_long_line(mut text: &[u8]) -> (&[u8], &[u8], &[u8]) {
+    let station_name: &[u8];
     let measurement_slice: &[u8];
-    (station_name_slice, text) = text.split_at(memchr(b';', &text[3..]).unwrap() + 3);
+    (station_name, text) = text.split_at(memchr(b';', &text[1..]).unwrap() + 1);
     text = &text[1..]; //skip ';';
     (measurement_slice, text) = text.split_at(memchr(b'\n', &text[3..]).unwrap() + 3);
     text = &text[1..]; //skip \n;
+    (text, station_name, measurement_slice)
+}
+
+/// A numeric column after the station name: a measurement in tenths as in the challenge, or a
+/// `Decimal` of any precision for `--sketch-accuracy`.
+pub(crate) trait Column: Copy {
+    fn parse(text: &[u8]) -> Self;
+    fn accepted_by(self, filter: &Filter) -> bool;
+}
+
+impl Column for i32 {
+    fn parse(text: &[u8]) -> i32 {
+        parse_measurement(text)
+    }
+
+    fn accepted_by(self, filter: &Filter) -> bool {
+        filter.accepts_tenths(self)
+    }
+}
+
+impl Column for Decimal {
+    fn parse(text: &[u8]) -> Decimal {
+        parse_decimal(text)
+    }
+
+    fn accepted_by(self, filter: &Filter) -> bool {
+        filter.accepts_value(self.value)
+    }
+}
+
+/// The numeric columns after the station name (and timestamp) of a line.
+pub(crate) trait Fields {
+    fn parse(text: &[u8]) -> Self;
+    /// Whether every column is within `--min-value` and `--max-value`.
+    fn accepted_by(&self, filter: &Filter) -> bool;
+}
+
+impl<C: Column> Fields for C {
+    fn parse(text: &[u8]) -> C {
+        <C as Column>::parse(text)
+    }
+
+    fn accepted_by(&self, filter: &Filter) -> bool {
+        Column::accepted_by(*self, filter)
+    }
+}
+
+impl<C: Column, const N: usize> Fields for [C; N] {
+    fn parse(text: &[u8]) -> [C; N] {
+        let mut columns = text.split(|&c| c == b';');
+        std::array::from_fn(|_| <C as Column>::parse(columns.next().expect("missing column")))
+    }
+
+    fn accepted_by(&self, filter: &Filter) -> bool {
+        self.iter().all(|column| column.accepted_by(filter))
+    }
+}
+
+fn read_line<M: Fields>(text: &[u8]) -> (&[u8], &[u8], M) {
+    let (remainder, station_name, measurement_slice) = unsafe { split_line(text) };
+    (remainder, station_name, M::parse(measurement_slice))
+}
+
+/// Like `read_line`, for `station;timestamp;value...` lines.
+fn read_timed_line<M: Fields>(text: &[u8]) -> (&[u8], &[u8], &[u8], M) {
+    let (remainder, station_name, fields) = unsafe { split_line(text) };
+    let separator_pos = memchr(b';', fields).expect("missing timestamp");
+    let measurement = M::parse(&fields[separator_pos + 1..]);
     (
-        text,
-        StationName {
-            ptr: station_name_slice.as_ptr(),
-            len: station_name_slice.len() as u8,
-        },
-        parse_measurement(measurement_slice),
+        remainder,
+        station_name,
+        &fields[..separator_pos],
+        measurement,
     )
 }
 
-fn process_chunk(chunk: &[u8]) -> MyPHFMap {
-    let mut summary = MyPHFMap::new();
+fn sample_names(lines: &[u8]) -> Vec<Vec<u8>> {
+    let sample_end = match lines.len() {
+        len if len <= SAMPLE_SIZE => len,
+        _ => memrchr(b'\n', &lines[..SAMPLE_SIZE]).unwrap() + 1,
+    };
+    let mut tail = Vec::new();
+    let mut names = FxHashSet::default();
+    for mut remainder in padded_chunks(&lines[..sample_end], 1, &mut tail) {
+        while remainder.len() != MARGIN {
+            let station_name: &[u8];
+            (remainder, station_name, _) = unsafe { split_line(remainder) };
+            names.insert(station_name);
+        }
+    }
+    names.into_iter().map(|name| name.to_vec()).collect()
+}
+
+pub(crate) fn process_chunk<'a, E: Aggregator<Measurement: Fields>>(
+    chunk: &[u8],
+    phf: &'a Phf,
+    options: &'a E::Options,
+    filter: Option<&Filter>,
+) -> MyPHFMap<'a, E> {
+    let mut summary = MyPHFMap::new(phf, options);
     let mut remainder = chunk;
+    let Some(filter) = filter else {
+        while remainder.len() != MARGIN {
+            let station_name: &[u8];
+            let measurement: E::Measurement;
+            (remainder, station_name, measurement) = read_line(remainder);
+            summary.insert_measurement(station_name, measurement);
+        }
+        return summary;
+    };
     while remainder.len() != MARGIN {
         let station_name: &[u8];
-        let measurement: i32;
-        (remainder, station_name, measurement) = unsafe { read_line(remainder) };
-        summary.insert_measurement(station_name, measurement);
+        let measurement: E::Measurement;
+        (remainder, station_name, measurement) = read_line(remainder);
+        if measurement.accepted_by(filter) {
+            summary.insert_filtered_measurement(station_name, measurement, filter);
+        }
     }
     summary
 }
 
-pub fn run(mut writer: PipeWriter) {
+fn process_timed_chunk<E: Aggregator<Measurement: Fields>>(
+    chunk: &[u8],
+    width: BucketWidth,
+    options: &E::Options,
+    phf: &Phf,
+    filter: Option<&Filter>,
+) -> MyHashMap<E, TimedName> {
+    let mut summary = MyHashMap::new(options);
+    let mut remainder = chunk;
+    while remainder.len() != MARGIN {
+        let station_name: &[u8];
+        let timestamp: &[u8];
+        let measurement: E::Measurement;
+        (remainder, station_name, timestamp, measurement) = read_timed_line(remainder);
+        if filter.is_some_and(|filter| {
+            !measurement.accepted_by(filter) || !filter.accepts_station(phf, station_name)
+        }) {
+            continue;
+        }
+        let key = TimedName {
+            name: StationName {
+                ptr: station_name.as_ptr(),
+                len: station_name.len() as u8,
+            },
+            bucket: width.get_bucket(parse_timestamp(timestamp)),
+        };
+        summary.insert_measurement(key, measurement);
+    }
+    summary
+}
+
+/// The default mode, or `aggregate`: aggregates measurements.txt with the thread count and
+/// options in `args`.
+pub fn run(mut writer: PipeWriter, mut args: impl Iterator<Item = String>) {
     let file = File::open("measurements.txt").expect("measurements.txt file not found");
-    let mapped_file = map_file(&file).unwrap();
-    let thread_count: usize = std::env::args()
-        .nth(1)
+    let thread_count: usize = args
+        .next()
         .expect("missing thread count")
         .parse()
         .expect("invalid thread count");
+    let mut phf = None;
+    let mut sample = false;
+    let mut checkpoint_path: Option<PathBuf> = None;
+    let mut follow = false;
+    let mut serve: Option<Address> = None;
+    let mut watch: Option<PathBuf> = None;
+    let mut interval = Some(Duration::from_secs(10));
+    let mut stats = false;
+    let mut percentiles: Option<Vec<Percentile>> = None;
+    let mut sketch_accuracy: Option<f64> = None;
+    let mut report = Report::default();
+    let mut include: Option<Vec<Vec<u8>>> = None;
+    let mut exclude = Vec::new();
+    let mut values = f64::NEG_INFINITY..=f64::INFINITY;
+    let mut tenths = i32::MIN..=i32::MAX;
+    // the first option of how the results are written, which queries choose with `--serve`
+    let mut report_arg: Option<String> = None;
+    while let Some(arg) = args.next() {
+        if report.parse_arg(&arg, &mut args) {
+            report_arg.get_or_insert(arg);
+            continue;
+        }
+        match arg.as_str() {
+            "--names" => {
+                let names = read_names_file(&args.next().expect("missing names file"));
+                phf = Some(Phf::build_or_empty(names));
+            }
+            "--sample-names" => sample = true,
+            "--checkpoint" => {
+                let path = args.next().expect("missing checkpoint path");
+                checkpoint_path = Some(path.into());
+            }
+            "--follow" => follow = true,
+            "--serve" => serve = Some(Address::parse(&args.next().expect("missing address"))),
+            "--watch" => watch = Some(args.next().expect("missing directory").into()),
+            "--interval" => {
+                let seconds: f64 = args
+                    .next()
+                    .expect("missing interval")
+                    .parse()
+                    .expect("invalid interval");
+                // 0 only writes a summary on SIGUSR1
+                interval = (seconds > 0.0).then(|| Duration::from_secs_f64(seconds));
+            }
+            "--stats" => stats = true,
+            "--percentiles" => {
+                let list = args.next().expect("missing percentiles");
+                percentiles = Some(list.split(',').map(|p| p.parse().unwrap()).collect());
+            }
+            "--sketch-accuracy" => {
+                let accuracy = args.next().expect("missing sketch accuracy");
+                sketch_accuracy = Some(accuracy.parse().expect("invalid sketch accuracy"));
+            }
+            "--time-buckets" => {
+                let width = args.next().expect("missing time bucket width");
+                report.time_buckets = Some(width.parse().unwrap());
+            }
+            "--columns" => {
+                let count = args.next().expect("missing column count");
+                report.layout.columns = count.parse().expect("invalid column count");
+            }
+            "--include" => {
+                let list = args.next().expect("missing station list");
+                let names = list.split(',').map(|name| name.as_bytes().to_vec());
+                include.get_or_insert_default().extend(names);
+            }
+            "--include-file" => {
+                let names = read_names_file(&args.next().expect("missing names file"));
+                include.get_or_insert_default().extend(names);
+            }
+            "--exclude" => {
+                let list = args.next().expect("missing station list");
+                exclude.extend(list.split(',').map(|name| name.as_bytes().to_vec()));
+            }
+            "--exclude-file" => {
+                exclude.extend(read_names_file(&args.next().expect("missing names file")));
+            }
+            "--min-value" => {
+                let (min, min_tenths) = parse_bound(&args.next().expect("missing min value"), true);
+                values = min..=*values.end();
+                tenths = min_tenths..=*tenths.end();
+            }
+            "--max-value" => {
+                let (max, max_tenths) =
+                    parse_bound(&args.next().expect("missing max value"), false);
+                values = *values.start()..=max;
+                tenths = *tenths.start()..=max_tenths;
+            }
+            _ => panic!("unknown argument: {arg}"),
+        }
+    }
+    report.layout.statistics = match (stats, percentiles, sketch_accuracy) {
+        (false, None, None) => Statistics::Basic,
+        (true, None, None) => Statistics::Stats,
+        (false, Some(percentiles), None) => Statistics::Percentiles(percentiles),
+        (false, Some(percentiles), Some(accuracy)) => Statistics::Sketch {
+            accuracy,
+            percentiles,
+        },
+        (_, None, Some(_)) => panic!("--sketch-accuracy needs --percentiles"),
+        (true, Some(_), _) => panic!("--stats and --percentiles cannot be combined"),
+    };
+    report.finish();
+    let selection = Selection::new(include, exclude, values, tenths);
+    assert!(serve.is_some() || watch.is_none(), "--watch needs --serve");
+    assert!(
+        serve.is_none() || !follow && report.time_buckets.is_none() && checkpoint_path.is_none(),
+        "--serve cannot be combined with --follow, --time-buckets or --checkpoint"
+    );
+    assert!(
+        serve.is_none() || report_arg.is_none(),
+        "--serve cannot be combined with {}, the queries choose what is written and how",
+        report_arg.as_deref().unwrap_or_default()
+    );
+    if follow {
+        assert!(
+            report.time_buckets.is_none() && checkpoint_path.is_none(),
+            "--follow cannot be combined with --time-buckets or --checkpoint"
+        );
+        // before the worker threads exist, so they inherit the blocked signal
+        follow::block_signals();
+    }
     rayon::ThreadPoolBuilder::new()
         .num_threads(thread_count)
         .build_global()
         .unwrap();
+    let checkpoint_bytes = checkpoint_path.as_ref().and_then(|path| {
+        assert!(
+            report.time_buckets.is_none(),
+            "--time-buckets cannot be checkpointed"
+        );
+        match std::fs::read(path) {
+            Ok(bytes) => Some(bytes),
+            // the first run starts the checkpoint
+            Err(error) if error.kind() == ErrorKind::NotFound => None,
+            Err(error) => panic!("{}: {error}", path.display()),
+        }
+    });
+    let previous = checkpoint_bytes.as_deref().map(|bytes| {
+        let previous = checkpoint::read(bytes).unwrap_or_else(|error| panic!("{error}"));
+        assert!(
+            previous.layout == report.layout,
+            "the checkpoint has different statistics or columns"
+        );
+        assert!(
+            previous.selection == selection,
+            "the checkpoint has a different --include, --exclude, --min-value or --max-value"
+        );
+        assert!(
+            previous.position.matches(&file).unwrap(),
+            "measurements.txt does not continue the checkpoint, it was truncated or replaced"
+        );
+        previous
+    });
+    let start = previous.as_ref().map_or(Position::START, |p| p.position);
+    let mapped_file = map_file(&file, start.offset).unwrap();
+    // a last line without a line break is still being appended, so it is left for the next run,
+    // read once complete, or else ignored
+    let lines = complete_lines(&mapped_file[..mapped_file.len() - MARGIN]);
+    let resume = checkpoint_path.map(|path| Resume {
+        path,
+        previous,
+        position: start.after(lines),
+        selection: selection.clone(),
+    });
+    let phf = match phf {
+        Some(phf) => phf,
+        None if sample => Phf::build_or_empty(sample_names(lines)),
+        None => Phf::station_names(),
+    };
+    if !selection.is_everything() {
+        report.filter = Some(Filter::new(&phf, &selection));
+    }
+    // the margin is only readable if the file does not end at a page boundary
+    let mut tail = Vec::new();
+    let chunks = padded_chunks(lines, thread_count, &mut tail);
+    if let Some(address) = serve {
+        let serve = Serve {
+            chunks,
+            address,
+            watch,
+            thread_count,
+            phf: &phf,
+            report: &report,
+        };
+        dispatch(&report.layout, serve);
+    } else if follow {
+        let follow = Follow {
+            chunks,
+            file: file.try_clone().unwrap(),
+            position: Position::START.after(lines),
+            thread_count,
+            interval,
+            phf: &phf,
+            report: &report,
+        };
+        dispatch(&report.layout, follow);
+    } else {
+        let aggregate = Aggregate {
+            chunks,
+            phf: &phf,
+            report: &report,
+            resume: resume.as_ref(),
+        };
+        dispatch(&report.layout, aggregate);
+    }
+    writer.write_all(&[0]).unwrap();
+}
+
+/// Which lines are counted, which statistics are kept, and how the aggregated results are
+/// keyed and written.
+pub(crate) struct Report {
+    pub(crate) filter: Option<Filter>,
+    time_buckets: Option<BucketWidth>,
+    pub(crate) layout: Layout,
+    group_by: Option<GroupBy>,
+    ranking: Option<Ranking>,
+    rank_by: Metric,
+    sort: Option<SortOrder>,
+    format: Format,
+    output: Destination,
+    /// `--partial`, where the unmerged station results are written instead.
+    partial: Option<PathBuf>,
+}
+
+impl Default for Report {
+    fn default() -> Report {
+        Report {
+            filter: None,
+            time_buckets: None,
+            layout: Layout {
+                statistics: Statistics::Basic,
+                columns: 1,
+            },
+            group_by: None,
+            ranking: None,
+            rank_by: Metric::Mean,
+            sort: None,
+            format: Format::default(),
+            output: Destination::Stdout,
+            partial: None,
+        }
+    }
+}
+
+impl Report {
+    /// Parses the options of how results are written, which are shared by `run` and `merge`.
+    /// Returns false for any other argument.
+    pub(crate) fn parse_arg(&mut self, arg: &str, args: &mut impl Iterator<Item = String>) -> bool {
+        match arg {
+            "--group-by" => {
+                let group_by = args.next().expect("missing group by");
+                self.group_by = Some(GroupBy::parse(&group_by));
+            }
+            "--drill-down" => {
+                let group_by = self.group_by.as_mut();
+                group_by
+                    .expect("--drill-down needs --group-by first")
+                    .drill_down = true;
+            }
+            "--top" | "--bottom" => {
+                let k = args.next().expect("missing ranking size");
+                self.ranking = Some(Ranking {
+                    metric: Metric::Mean,
+                    descending: arg == "--top",
+                    k: k.parse().expect("invalid ranking size"),
+                });
+            }
+            "--rank-by" => {
+                self.rank_by = args
+                    .next()
+                    .expect("missing ranking metric")
+                    .parse()
+                    .unwrap();
+            }
+            "--sort" => {
+                self.sort = Some(args.next().expect("missing sort order").parse().unwrap());
+            }
+            "--format" => {
+                self.format = args.next().expect("missing format").parse().unwrap();
+            }
+            "--output" => {
+                let path = args.next().expect("missing output path");
+                self.output = Destination::File(path.into());
+            }
+            "--partial" => {
+                let path = args.next().expect("missing partial summary path");
+                self.partial = Some(path.into());
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    /// Checks the combination of options once they are all parsed.
+    pub(crate) fn finish(&mut self) {
+        assert!(
+            self.time_buckets.is_none() || self.group_by.is_none(),
+            "--time-buckets and --group-by cannot be combined"
+        );
+        assert!(
+            self.time_buckets.is_none() || self.ranking.is_none(),
+            "--time-buckets cannot be ranked"
+        );
+        assert!(
+            self.time_buckets.is_none() || self.partial.is_none(),
+            "--time-buckets cannot be written as a partial summary"
+        );
+        if let Some(ranking) = &mut self.ranking {
+            ranking.metric = self.rank_by;
+        }
+    }
+
+    /// Writes station results sorted by name, grouped and ranked, or as a partial summary.
+    ///
+    /// # Panics
+    /// If the results could not be written.
+    pub(crate) fn write_results<E: Aggregator>(
+        &self,
+        results: Vec<(&[u8], &E)>,
+        options: &E::Options,
+    ) {
+        if let Some(path) = &self.partial {
+            Destination::File(path.clone())
+                .write(|out| partial::write(out, &self.layout, &results))
+                .expect("failed to write the partial summary");
+            return;
+        }
+        let ranking = self.ranking.as_ref();
+        match &self.group_by {
+            None => self.write(Summary::stations(results, options, ranking)),
+            Some(group_by) => self.write(group_by.summary(results, options, ranking)),
+        }
+    }
+
+    /// Writes the station results of `--follow`, followed by a line break on stdout so every
+    /// summary is on its own line.
+    pub(crate) fn write_update<E: Aggregator>(
+        &self,
+        results: Vec<(&[u8], &E)>,
+        options: &E::Options,
+    ) {
+        self.write_results(results, options);
+        if self.partial.is_none() && matches!(self.output, Destination::Stdout) {
+            std::io::stdout()
+                .write_all(b"\n")
+                .expect("failed to write the results");
+        }
+    }
+
+    /// # Panics
+    /// If the results could not be written, so the run fails instead of leaving a partial or
+    /// missing output behind silently.
+    fn write<E: Aggregator>(&self, mut summary: Summary<E>) {
+        if let Some(order) = &self.sort {
+            order.sort(&mut summary.rows);
+        }
+        self.output
+            .write(|mut out| summary.write_to(&mut out, self.format))
+            .expect("failed to write the results");
+    }
+}
+
+/// Work that needs the aggregator picked at runtime by the layout.
+pub(crate) trait Task {
+    fn run<E: Aggregator<Measurement: Fields>>(self, options: &E::Options);
+}
+
+/// Runs `task` with the aggregator of the layout's statistics and column count.
+pub(crate) fn dispatch(layout: &Layout, task: impl Task) {
+    match &layout.statistics {
+        Statistics::Basic => dispatch_columns::<StationEntry>(layout.columns, &(), task),
+        Statistics::Stats => dispatch_columns::<StatsEntry>(layout.columns, &(), task),
+        Statistics::Percentiles(percentiles) => {
+            dispatch_columns::<HistogramEntry>(layout.columns, percentiles, task)
+        }
+        Statistics::Sketch {
+            accuracy,
+            percentiles,
+        } => {
+            let options = SketchOptions::new(*accuracy, percentiles.clone());
+            dispatch_columns::<SketchEntry>(layout.columns, &options, task)
+        }
+    }
+}
+
+/// Dispatches the runtime column count to `task` monomorphized for it.
+fn dispatch_columns<E: Aggregator<Measurement: Column>>(
+    columns: usize,
+    options: &E::Options,
+    task: impl Task,
+) {
+    match columns {
+        1 => task.run::<E>(options),
+        2 => task.run::<Columns<E, 2>>(options),
+        3 => task.run::<Columns<E, 3>>(options),
+        4 => task.run::<Columns<E, 4>>(options),
+        _ => panic!("only 1 to 4 columns are supported"),
+    }
+}
+
+/// `--checkpoint`, for a measurements file that is only appended to: the checkpoint of the
+/// lines aggregated by previous runs, and the next one, after the lines of this run.
+struct Resume<'a> {
+    path: PathBuf,
+    previous: Option<Checkpoint<'a>>,
+    position: Position,
+    selection: Selection,
+}
+
+impl Resume<'_> {
+    /// Merges the results of this run into those of the previous checkpoint, writes them, and
+    /// only then replaces the checkpoint, so a failed run is repeated by the next one.
+    fn write_results<E: Aggregator>(
+        &self,
+        results: Vec<(&[u8], &E)>,
+        options: &E::Options,
+        report: &Report,
+    ) {
+        let mut stations: BTreeMap<&[u8], E> = match &self.previous {
+            Some(previous) => partial::read_entries(previous.entries, options)
+                .unwrap_or_else(|error| panic!("{}: {error}", self.path.display()))
+                .into_iter()
+                .collect(),
+            None => BTreeMap::new(),
+        };
+        for (name, entry) in results {
+            match stations.get_mut(name) {
+                Some(station) => station.merge(entry),
+                None => _ = stations.insert(name, entry.clone()),
+            }
+        }
+        let results: Vec<(&[u8], &E)> = stations
+            .iter()
+            .map(|(&name, entry)| (name, entry))
+            .collect();
+        report.write_results(results.clone(), options);
+        Destination::File(self.path.clone())
+            .write(|out| {
+                let (position, selection) = (self.position, &self.selection);
+                checkpoint::write(out, position, &report.layout, selection, &results)
+            })
+            .expect("failed to write the checkpoint");
+    }
+}
+
+struct Aggregate<'a, I> {
+    chunks: I,
+    phf: &'a Phf,
+    report: &'a Report,
+    resume: Option<&'a Resume<'a>>,
+}
+
+impl<'a, I: Iterator<Item = &'a [u8]> + Send> Task for Aggregate<'a, I> {
+    fn run<E: Aggregator<Measurement: Fields>>(self, options: &E::Options) {
+        summarize::<E>(self.chunks, self.phf, options, self.report, self.resume);
+    }
+}
+
+/// Aggregates the chunks per station, or per station and time bucket, and writes the results.
+fn summarize<'a, E: Aggregator<Measurement: Fields>>(
+    chunks: impl Iterator<Item = &'a [u8]> + Send,
+    phf: &Phf,
+    options: &E::Options,
+    report: &Report,
+    resume: Option<&Resume>,
+) {
+    let Some(width) = report.time_buckets else {
+        let map = aggregate_chunks::<E>(chunks, phf, options, report.filter.as_ref());
+        match resume {
+            Some(resume) => resume.write_results(map.results(), options, report),
+            None => report.write_results(map.results(), options),
+        }
+        return;
+    };
+    let map = chunks
+        .par_bridge()
+        .map(|chunk| process_timed_chunk::<E>(chunk, width, options, phf, report.filter.as_ref()))
+        .reduce(
+            || MyHashMap::new(options),
+            |mut a, b| {
+                for (key, entry) in b.iter() {
+                    a.merge_entry(key, entry);
+                }
+                a
+            },
+        );
+    report.write(time_buckets::summary(&map, width, options));
+}
+
+/// Aggregates the chunks per station on the thread pool.
+pub(crate) fn aggregate_chunks<'a, 'b, E: Aggregator<Measurement: Fields>>(
+    chunks: impl Iterator<Item = &'b [u8]> + Send,
+    phf: &'a Phf,
+    options: &'a E::Options,
+    filter: Option<&Filter>,
+) -> MyPHFMap<'a, E> {
+    chunks
+        .par_bridge()
+        .map(|chunk| process_chunk::<E>(chunk, phf, options, filter))
+        .reduce(
+            || MyPHFMap::new(phf, options),
+            |mut a, b| {
+                a.merge_maps(b);
+                a
+            },
+        )
+}
+
+/// Splits the file at line breaks into more chunks than threads, each followed by `MARGIN`
+/// readable bytes.
+pub(crate) fn split_chunks(
+    mapped_file: &[u8],
+    thread_count: usize,
+) -> impl Iterator<Item = &[u8]> + Send {
     let chunks_mult = 16;
-    let chunks = thread_count * chunks_mult;
+    let chunks = (thread_count * chunks_mult)
+        .min(mapped_file.len() / MIN_CHUNK_SIZE)
+        .max(1);
     let ideal_chunk_size = mapped_file.len() / chunks;
     let mut remainder = mapped_file;
-    let final_summary = (0..chunks)
-        .map(|_| {
-            let chunk_end = memrchr(b'\n', &remainder[..ideal_chunk_size]).unwrap();
-            let chunk: &[u8] = &remainder[..chunk_end + MARGIN + 1];
-            remainder = &remainder[chunk_end + 1..];
-            chunk
-        })
-        .par_bridge()
-        .map(process_chunk)
-        .reduce(MyPHFMap::new, |mut a, b| {
-            a.merge_maps(b);
-            a
-        });
-    final_summary.print_results();
-    writer.write_all(&[0]).unwrap();
+    (0..chunks).map(move |chunk_index| {
+        if chunk_index == chunks - 1 {
+            // the last chunk takes every line the previous chunks stopped short of
+            return remainder;
+        }
+        let chunk_end = memrchr(b'\n', &remainder[..ideal_chunk_size]).unwrap();
+        let chunk: &[u8] = &remainder[..chunk_end + MARGIN + 1];
+        remainder = &remainder[chunk_end + 1..];
+        chunk
+    })
+}
+
+/// `split_chunks` for lines that are not followed by `MARGIN` readable bytes, like a mapped file
+/// whose size is a multiple of the page size: the last lines are copied into `tail`, followed by
+/// the margin. A last line without a line break is left out.
+pub(crate) fn padded_chunks<'a>(
+    lines: &'a [u8],
+    thread_count: usize,
+    tail: &'a mut Vec<u8>,
+) -> impl Iterator<Item = &'a [u8]> + Send {
+    let end = memrchr(b'\n', lines).map_or(0, |end| end + 1);
+    let in_place = memrchr(b'\n', &lines[..end.saturating_sub(MARGIN)]).map_or(0, |end| end + 1);
+    tail.clear();
+    tail.extend_from_slice(&lines[in_place..end]);
+    tail.resize(tail.len() + MARGIN, 0);
+    (in_place > 0)
+        .then(|| split_chunks(&lines[..in_place + MARGIN], thread_count))
+        .into_iter()
+        .flatten()
+        .chain([&tail[..]])
+}
+
+/// Station results that outlive the lines they were aggregated from.
+pub type OwnedResults = Vec<(Box<[u8]>, StationEntry)>;
+
+/// The statistics of every station in `station;measurement` lines, in name order, aggregated on
+/// a pool of `threads` threads, or one per CPU if 0, for programs that embed the aggregation. The
+/// lines are read in place as far as `MARGIN` bytes follow them, and the rest from a padded copy.
+/// A last line without a line break is ignored.
+pub fn aggregate_bytes(lines: &[u8], threads: usize) -> Result<OwnedResults, Error> {
+    // a pool of its own, as the global one belongs to the program
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(threads)
+        .build()
+        .map_err(Error::other)?;
+    let mut tail = Vec::new();
+    let chunks = padded_chunks(lines, pool.current_num_threads(), &mut tail);
+    let phf = Phf::station_names();
+    let map = pool.install(|| aggregate_chunks::<StationEntry>(chunks, &phf, &(), None));
+    // the names of unknown stations point into the lines
+    Ok(map
+        .results()
+        .into_iter()
+        .map(|(name, entry)| (name.into(), *entry))
+        .collect())
+}
+
+/// `aggregate_bytes` for the lines of the file at `path`, which is mapped instead of read.
+pub fn aggregate_file(path: &Path, threads: usize) -> Result<OwnedResults, Error> {
+    let file = File::open(path)?;
+    let mapped_file = map_file(&file, 0)?;
+    let results = aggregate_bytes(&mapped_file[..mapped_file.len() - MARGIN], threads);
+    unsafe { unmap_file(mapped_file) };
+    results
 }
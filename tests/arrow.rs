@@ -0,0 +1,59 @@
+#![cfg(feature = "arrow")]
+
+use arrow_array::{Float64Array, RecordBatch, StringArray, UInt64Array};
+use arrow_ipc::reader::FileReader;
+use parquet::arrow::arrow_reader::ParquetRecordBatchReader;
+
+#[allow(dead_code)]
+mod common;
+
+const MEASUREMENTS: &[u8] = "Abha;1.0\nAccra;-2.0\nAbha;2.0\n".as_bytes();
+
+fn run(name: &str, args: &[&str]) -> Vec<u8> {
+    let args = [&["4"], args].concat();
+    common::run_binary(name, &args, |file| file.write_all(MEASUREMENTS).unwrap())
+}
+
+fn column<'a, T: 'static>(batch: &'a RecordBatch, name: &str) -> &'a T {
+    let column = batch.column_by_name(name).expect("missing column");
+    column.as_any().downcast_ref().expect("wrong column type")
+}
+
+#[test]
+fn arrow_ipc() {
+    let output = run("arrow-ipc", &["--format", "arrow", "--stats"]);
+    let mut reader = FileReader::try_new(std::io::Cursor::new(output), None).unwrap();
+    let batch = reader.next().unwrap().unwrap();
+    let names: Vec<_> = column::<StringArray>(&batch, "station").iter().collect();
+    assert_eq!(names, [Some("Abha"), Some("Accra")]);
+    let means: Vec<_> = column::<Float64Array>(&batch, "mean").values().to_vec();
+    assert_eq!(means, [1.5, -2.0]);
+    let counts: Vec<_> = column::<UInt64Array>(&batch, "count").values().to_vec();
+    assert_eq!(counts, [2, 1]);
+    let stddevs: Vec<_> = column::<Float64Array>(&batch, "stddev").values().to_vec();
+    assert_eq!(stddevs, [0.5, 0.0]);
+}
+
+#[test]
+fn parquet() {
+    let output = run(
+        "parquet",
+        &[
+            "--format",
+            "parquet",
+            "--group-by",
+            "prefix:1",
+            "--drill-down",
+        ],
+    );
+    let path = std::env::temp_dir().join(format!("1brc-{}.parquet", std::process::id()));
+    std::fs::write(&path, output).unwrap();
+    let file = std::fs::File::open(&path).unwrap();
+    let mut reader = ParquetRecordBatchReader::try_new(file, 1024).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    let batch = reader.next().unwrap().unwrap();
+    let stations: Vec<_> = column::<StringArray>(&batch, "station").iter().collect();
+    assert_eq!(stations, [None, Some("Abha"), Some("Accra")]);
+    let maxes: Vec<_> = column::<Float64Array>(&batch, "max").values().to_vec();
+    assert_eq!(maxes, [2.0, 2.0, -2.0]);
+}
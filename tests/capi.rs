@@ -0,0 +1,61 @@
+use std::{fs, process::Command};
+
+const HEADER: &str = "include/brc.h";
+
+#[test]
+fn header_is_up_to_date() {
+    let config = cbindgen::Config::from_file("cbindgen.toml").unwrap();
+    let mut header = Vec::new();
+    cbindgen::Builder::new()
+        .with_config(config)
+        .with_src("src/capi.rs")
+        .generate()
+        .unwrap()
+        .write(&mut header);
+    if std::env::var_os("BRC_UPDATE_HEADER").is_some() {
+        fs::write(HEADER, &header).unwrap();
+    }
+    let header = String::from_utf8(header).unwrap();
+    assert!(
+        fs::read_to_string(HEADER).unwrap() == header,
+        "{HEADER} is out of date, regenerate it with BRC_UPDATE_HEADER=1"
+    );
+}
+
+/// Builds tests/capi/brc_test.c against the library and runs it on `measurements`.
+fn run_c_test(measurements: &str) -> String {
+    let dir = std::env::temp_dir().join(format!("1brc-capi-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("measurements.txt"), measurements).unwrap();
+    // cargo builds the library next to the tests, but only copies it to target/ on a build
+    let test = std::env::current_exe().unwrap();
+    let library_dir = test.parent().unwrap();
+    let status = Command::new("cc")
+        .args(["-std=c11", "-Wall", "-Wextra", "-Werror", "-Iinclude"])
+        .arg("tests/capi/brc_test.c")
+        .arg("-L")
+        .arg(library_dir)
+        .arg(format!("-Wl,-rpath,{}", library_dir.display()))
+        .arg("-lone_billion_row_challange")
+        .arg("-o")
+        .arg(dir.join("brc_test"))
+        .status()
+        .unwrap();
+    assert!(status.success(), "compiling brc_test.c failed");
+    let output = Command::new(dir.join("brc_test"))
+        .arg(dir.join("measurements.txt"))
+        .output()
+        .unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+    assert!(output.status.success(), "brc_test failed");
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn c_program() {
+    assert_eq!(
+        run_c_test("Abha;1.0\nZürich;-7.5\nAbha;2.5\nAbha;-4.0\n"),
+        "Abha;-4.0;-0.2000;2.5;3\n\
+         Zürich;-7.5;-7.5000;-7.5;1\n"
+    );
+}
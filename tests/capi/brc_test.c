@@ -0,0 +1,38 @@
+/* Aggregates the file given as the only argument through the C ABI, and prints every row as
+ * `name;min;mean;max;count`. Run by tests/capi.rs. */
+#include <assert.h>
+#include <stdio.h>
+#include <string.h>
+
+#include "brc.h"
+
+int main(int argc, char **argv) {
+    if (argc != 2) {
+        fprintf(stderr, "usage: %s FILE\n", argv[0]);
+        return 2;
+    }
+
+    BrcResults *results = NULL;
+    assert(brc_aggregate_file(NULL, NULL, &results) == BRC_STATUS_INVALID_ARGUMENT);
+    assert(brc_aggregate_file("does-not-exist.txt", NULL, &results) == BRC_STATUS_IO_ERROR);
+    assert(strstr(brc_last_error(), "does-not-exist.txt") != NULL);
+    assert(results == NULL);
+
+    BrcOptions options = {.threads = 2};
+    if (brc_aggregate_file(argv[1], &options, &results) != BRC_STATUS_OK) {
+        fprintf(stderr, "%s\n", brc_last_error());
+        return 1;
+    }
+    BrcRow row;
+    size_t len = 0;
+    while (brc_results_row(results, len, &row)) {
+        assert(strlen(row.name) == row.name_len);
+        printf("%s;%.1f;%.4f;%.1f;%llu\n", row.name, row.min, row.mean, row.max,
+               (unsigned long long)row.count);
+        len++;
+    }
+    assert(len == brc_results_len(results));
+    brc_results_free(results);
+    brc_results_free(NULL);
+    return 0;
+}
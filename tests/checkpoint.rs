@@ -0,0 +1,99 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+mod common;
+
+const FIRST: &str = "Abha;1.0\nZürich;7.0\nAbha;2.";
+const APPENDED: &str = "0\nNowhere;5.5\nAbha;-1.0\n";
+
+fn checkpoint_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("1brc-{name}-{}.bin", std::process::id()))
+}
+
+/// Runs over `measurements`, continuing the checkpoint at `path`.
+fn run(name: &str, path: &Path, args: &[&str], measurements: &str) -> String {
+    let args = [&["4", "--checkpoint", path.to_str().unwrap()], args].concat();
+    common::run(name, &args, |file| {
+        file.write_all(measurements.as_bytes()).unwrap()
+    })
+}
+
+#[test]
+fn appended_lines() {
+    let path = checkpoint_path("checkpoint-appended");
+    for args in [&[][..], &["--percentiles", "50"]] {
+        _ = fs::remove_file(&path);
+        // the incomplete last line is left for the next run
+        let first = run("checkpoint-first", &path, args, FIRST);
+        let full = common::run("checkpoint-full", &[&["4"], args].concat(), |file| {
+            file.write_all("Abha;1.0\nZürich;7.0\n".as_bytes()).unwrap()
+        });
+        assert_eq!(first, full, "{args:?}");
+        let measurements = FIRST.to_string() + APPENDED;
+        let appended = run("checkpoint-appended", &path, args, &measurements);
+        let full = common::run("checkpoint-full", &[&["4"], args].concat(), |file| {
+            file.write_all(measurements.as_bytes()).unwrap()
+        });
+        assert_eq!(appended, full, "{args:?}");
+        // nothing new
+        let again = run("checkpoint-again", &path, args, &measurements);
+        assert_eq!(again, full, "{args:?}");
+    }
+    fs::remove_file(path).unwrap();
+}
+
+#[test]
+#[should_panic(expected = "checkpoint-replaced failed")]
+fn replaced_file() {
+    let path = checkpoint_path("checkpoint-replaced");
+    _ = fs::remove_file(&path);
+    run("checkpoint-replaced-first", &path, &[], FIRST);
+    let result = std::panic::catch_unwind(|| {
+        run("checkpoint-replaced", &path, &[], "Abha;1.0\nZürich;8.0\n")
+    });
+    fs::remove_file(path).unwrap();
+    std::panic::resume_unwind(result.unwrap_err());
+}
+
+#[test]
+#[should_panic(expected = "checkpoint-filter failed")]
+fn different_filter() {
+    let path = checkpoint_path("checkpoint-filter");
+    _ = fs::remove_file(&path);
+    // the same stations in another order are the same selection
+    let first = ["--include", "Abha,Zürich", "--min-value", "0"];
+    run("checkpoint-filter-first", &path, &first, FIRST);
+    let reordered = ["--include", "Zürich,Abha", "--min-value", "0.0"];
+    run("checkpoint-filter-same", &path, &reordered, FIRST);
+    let result =
+        std::panic::catch_unwind(|| run("checkpoint-filter", &path, &["--include", "Abha"], FIRST));
+    fs::remove_file(path).unwrap();
+    std::panic::resume_unwind(result.unwrap_err());
+}
+
+#[test]
+fn invalid_utf8() {
+    let path = checkpoint_path("checkpoint-utf8");
+    _ = fs::remove_file(&path);
+    let args = ["4", "--checkpoint", path.to_str().unwrap()];
+    let first: &[u8] = b"Ab\xffha;1.0\nZ\xfcrich;7.0\n";
+    let measurements = [first, b"Ab\xffha;-3.0\n"].concat();
+    common::run("checkpoint-utf8-first", &args, |file| {
+        file.write_all(first).unwrap()
+    });
+    // the names in the checkpoint are read back as they were written
+    let resumed = common::run("checkpoint-utf8", &args, |file| {
+        file.write_all(&measurements).unwrap()
+    });
+    fs::remove_file(path).unwrap();
+    let full = common::run("checkpoint-utf8-full", &["4"], |file| {
+        file.write_all(&measurements).unwrap()
+    });
+    assert_eq!(resumed, full);
+    assert_eq!(
+        full,
+        "{Ab\u{fffd}ha=-3.0/-1.0/1.0, Z\u{fffd}rich=7.0/7.0/7.0}"
+    );
+}
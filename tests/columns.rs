@@ -0,0 +1,32 @@
+mod common;
+
+#[test]
+fn three_columns() {
+    let output = common::run("columns", &["4", "--columns", "3"], |file| {
+        file.write_all(
+            b"Abha;20.5;45.0;-1.5\nNowhere;-3.0;80.2;10.0\nAbha;30.5;55.0;2.5\n\
+              Llanfairpwllgwyngyllgogerychwyndrobwllllantysiliogogogoch;1.0;2.0;3.0\n",
+        )
+        .unwrap();
+    });
+    assert_eq!(
+        output,
+        "{Abha=20.5/25.5/30.5;45.0/50.0/55.0;-1.5/0.5/2.5, \
+         Llanfairpwllgwyngyllgogerychwyndrobwllllantysiliogogogoch=1.0/1.0/1.0;2.0/2.0/2.0;\
+         3.0/3.0/3.0, \
+         Nowhere=-3.0/-3.0/-3.0;80.2/80.2/80.2;10.0/10.0/10.0}"
+    );
+}
+
+#[test]
+fn columns_per_time_bucket() {
+    let args = ["4", "--columns", "2", "--time-buckets", "day", "--stats"];
+    let output = common::run("columns-time-buckets", &args, |file| {
+        file.write_all(b"Abha;2024-03-05T01:00:00Z;1.0;-1.0\nAbha;2024-03-05T23:00:00Z;3.0;-3.0\n")
+            .unwrap();
+    });
+    assert_eq!(
+        output,
+        "{Abha={2024-03-05=1.0/2.0/3.0/1.0;-3.0/-2.0/-1.0/1.0}}"
+    );
+}
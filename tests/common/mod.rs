@@ -0,0 +1,33 @@
+use std::{
+    fs::{self, File},
+    io::{BufWriter, Write},
+    process::Command,
+};
+
+/// Runs the binary with `args` in a fresh directory whose measurements.txt is filled in by
+/// `write_measurements`, and returns what it printed. Panics if it fails.
+pub fn run(name: &str, args: &[&str], write_measurements: impl FnOnce(&mut dyn Write)) -> String {
+    String::from_utf8(run_binary(name, args, write_measurements)).unwrap()
+}
+
+/// `run` for output that is not text.
+pub fn run_binary(
+    name: &str,
+    args: &[&str],
+    write_measurements: impl FnOnce(&mut dyn Write),
+) -> Vec<u8> {
+    let dir = std::env::temp_dir().join(format!("1brc-{name}-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let mut file = BufWriter::new(File::create(dir.join("measurements.txt")).unwrap());
+    write_measurements(&mut file);
+    file.into_inner().unwrap().sync_all().unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_one-billion-row-challange"))
+        .args(args)
+        .current_dir(&dir)
+        .output()
+        .unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+    assert!(output.status.success(), "{name} failed");
+    output.stdout
+}
@@ -0,0 +1,84 @@
+use std::fs;
+
+mod common;
+
+const MEASUREMENTS: &[u8] =
+    b"Abha;1.0\nAccra;3.0\nZagreb;50.0\nNowhere;-1.0\nAbha;-60.5\nAccra;20.0\nNowhere;2.0\n";
+
+fn run(name: &str, args: &[&str]) -> String {
+    let args: Vec<&str> = ["4"].iter().chain(args).copied().collect();
+    common::run(name, &args, |file| file.write_all(MEASUREMENTS).unwrap())
+}
+
+#[test]
+fn include_and_exclude() {
+    // Nowhere is not one of the known stations, so it is checked by name
+    assert_eq!(
+        run("filter-include", &["--include", "Abha,Nowhere"]),
+        "{Abha=-60.5/-29.7/1.0, Nowhere=-1.0/0.5/2.0}"
+    );
+    assert_eq!(
+        run("filter-exclude", &["--exclude", "Accra,Nowhere"]),
+        "{Abha=-60.5/-29.7/1.0, Zagreb=50.0/50.0/50.0}"
+    );
+
+    let path = std::env::temp_dir().join(format!("1brc-include-{}.txt", std::process::id()));
+    fs::write(&path, "Zagreb\nAccra\n").unwrap();
+    let path = path.to_str().unwrap();
+    let output = run(
+        "filter-file",
+        &["--include-file", path, "--exclude", "Accra"],
+    );
+    fs::remove_file(path).unwrap();
+    assert_eq!(output, "{Zagreb=50.0/50.0/50.0}");
+}
+
+#[test]
+fn value_range() {
+    assert_eq!(
+        run(
+            "filter-values",
+            &["--min-value", "-1", "--max-value", "20.0"]
+        ),
+        "{Abha=1.0/1.0/1.0, Accra=3.0/11.5/20.0, Nowhere=-1.0/0.5/2.0}"
+    );
+    // bounds between tenths leave out the measurements just beyond them
+    assert_eq!(
+        run(
+            "filter-values-between",
+            &["--min-value", "1.04", "--max-value", "19.96"]
+        ),
+        "{Accra=3.0/3.0/3.0, Nowhere=2.0/2.0/2.0}"
+    );
+    assert_eq!(
+        run(
+            "filter-values-negative",
+            &["--min-value", "-0.96", "--max-value", "1.001"]
+        ),
+        "{Abha=1.0/1.0/1.0}"
+    );
+}
+
+#[test]
+fn filtered_time_buckets() {
+    let output = common::run(
+        "filter-time-buckets",
+        &[
+            "4",
+            "--time-buckets",
+            "day",
+            "--exclude",
+            "Abha",
+            "--max-value",
+            "5",
+        ],
+        |file| {
+            file.write_all(b"Abha;0;1.0\nAccra;0;3.0\nAccra;0;9.0\nNowhere;86400;2.0\n")
+                .unwrap()
+        },
+    );
+    assert_eq!(
+        output,
+        "{Accra={1970-01-01=3.0/3.0/3.0}, Nowhere={1970-01-02=2.0/2.0/2.0}}"
+    );
+}
@@ -0,0 +1,59 @@
+use std::process::Command;
+
+use one_billion_row_challange::{
+    my_phf::Phf,
+    phf_search::{PhfHash, SearchSpace, search_in},
+    station_names::STATION_NAMES,
+};
+
+#[test]
+fn hash_families() {
+    let names: Vec<Vec<u8>> = STATION_NAMES.iter().map(|name| name.to_vec()).collect();
+    // only the sample the station names are known to be told apart by, to keep it quick
+    let space = SearchSpace {
+        offsets: 1..2,
+        widths: 8..9,
+        ..SearchSpace::default()
+    };
+    let families = [
+        vec![PhfHash::Modulo],
+        (0..4).map(|seed| PhfHash::FxHash { seed }).collect(),
+        vec![PhfHash::Pext {
+            mask: 0x7f7f7f7f7f7f7f7f,
+        }],
+    ];
+    for hashes in families {
+        let parameters = search_in(&names, &space, &hashes, |_| {}).unwrap();
+        let phf = Phf::new(names.clone(), parameters);
+        for (name, index) in phf.names() {
+            // the hash reads past the name
+            let mut padded = name.to_vec();
+            padded.resize(name.len() + 64, 0);
+            let name = &padded[..name.len()];
+            assert_eq!(phf.get_name_index(name), index);
+            assert!(phf.is_name_at(index, name));
+        }
+    }
+}
+
+#[test]
+fn subcommand() {
+    let output = Command::new(env!("CARGO_BIN_EXE_one-billion-row-challange"))
+        .args([
+            "find-phf",
+            "station_names.txt",
+            "--hash",
+            "fxhash",
+            "--seeds",
+            "4",
+        ])
+        .args(["--offsets", "1", "--widths", "8"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let constants = String::from_utf8(output.stdout).unwrap();
+    assert!(
+        constants.contains("pub const STATION_NAMES_HASH: PhfHash = PhfHash::FxHash { seed: "),
+        "{constants}"
+    );
+}
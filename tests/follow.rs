@@ -0,0 +1,54 @@
+use std::{
+    fs::{self, OpenOptions},
+    io::{BufRead, BufReader, Write},
+    process::{Command, Stdio},
+};
+
+#[test]
+fn follow() {
+    let dir = std::env::temp_dir().join(format!("1brc-follow-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("measurements.txt");
+    fs::write(&path, "Abha;1.0\nZürich;7.0\nAb").unwrap();
+    let mut child = Command::new(env!("CARGO_BIN_EXE_one-billion-row-challange"))
+        .args(["4", "--follow", "--interval", "0"])
+        .current_dir(&dir)
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut summaries = BufReader::new(child.stdout.take().unwrap()).lines();
+    // written after SIGUSR1 is blocked, so it can be sent from then on
+    let first = summaries.next().unwrap().unwrap();
+    let mut next_summary = |update: &dyn Fn()| {
+        update();
+        // the appended lines are read before the summary the signal asks for
+        unsafe { libc::kill(child.id() as i32, libc::SIGUSR1) };
+        summaries.next().unwrap().unwrap()
+    };
+    let summaries = [
+        first,
+        next_summary(&|| {
+            let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+            file.write_all(b"ha;3.0\nNowhere;2.0\n").unwrap();
+        }),
+        // rotated
+        next_summary(&|| {
+            fs::rename(&path, dir.join("measurements.1.txt")).unwrap();
+            fs::write(&path, "Abha;5.0\n").unwrap();
+        }),
+        // truncated in place
+        next_summary(&|| fs::write(&path, "Zürich;1.0\n").unwrap()),
+    ];
+    child.kill().unwrap();
+    child.wait().unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(
+        summaries,
+        [
+            "{Abha=1.0/1.0/1.0, Zürich=7.0/7.0/7.0}",
+            "{Abha=1.0/2.0/3.0, Nowhere=2.0/2.0/2.0, Zürich=7.0/7.0/7.0}",
+            "{Abha=1.0/3.0/5.0, Nowhere=2.0/2.0/2.0, Zürich=7.0/7.0/7.0}",
+            "{Abha=1.0/3.0/5.0, Nowhere=2.0/2.0/2.0, Zürich=1.0/4.0/7.0}",
+        ]
+    );
+}
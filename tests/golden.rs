@@ -0,0 +1,20 @@
+use std::{fs, path::Path};
+
+mod common;
+
+// each tests/golden/<case>.txt is a measurements file, and <case>.out is what the reference Java
+// implementation printed for it
+const CASES: [&str; 4] = ["rounding", "negative_zero", "names", "extremes"];
+
+#[test]
+fn matches_reference_output() {
+    let golden = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden");
+    for case in CASES {
+        let measurements = fs::read(golden.join(format!("{case}.txt"))).unwrap();
+        let expected = fs::read_to_string(golden.join(format!("{case}.out"))).unwrap();
+        let output = common::run(&format!("golden-{case}"), &["4"], |file| {
+            file.write_all(&measurements).unwrap()
+        });
+        assert_eq!(output, expected.trim_end(), "{case}");
+    }
+}
@@ -0,0 +1,63 @@
+use std::fs;
+
+mod common;
+
+const MEASUREMENTS: &[u8] =
+    "Abha;1.0\nAccra;3.0\nZagreb;5.0\nZürich;7.0\nNowhere;-1.0\nAbha;2.0\n".as_bytes();
+
+fn run(name: &str, group_by: &str, drill_down: bool) -> String {
+    let mut args = vec!["4", "--group-by", group_by];
+    if drill_down {
+        args.push("--drill-down");
+    }
+    common::run(name, &args, |file| file.write_all(MEASUREMENTS).unwrap())
+}
+
+#[test]
+fn prefix() {
+    assert_eq!(
+        run("group-prefix", "prefix:1", false),
+        "{A=1.0/2.0/3.0, N=-1.0/-1.0/-1.0, Z=5.0/6.0/7.0}"
+    );
+    // "Zü" is 3 bytes, so a 2 byte prefix of Zürich is cut back to "Z"
+    assert_eq!(
+        run("group-prefix-utf8", "prefix:2", true),
+        "{Ab=1.0/1.5/2.0 {Abha=1.0/1.5/2.0}, Ac=3.0/3.0/3.0 {Accra=3.0/3.0/3.0}, \
+         No=-1.0/-1.0/-1.0 {Nowhere=-1.0/-1.0/-1.0}, Z=7.0/7.0/7.0 {Zürich=7.0/7.0/7.0}, \
+         Za=5.0/5.0/5.0 {Zagreb=5.0/5.0/5.0}}"
+    );
+}
+
+#[test]
+fn lookup_file() {
+    let path = std::env::temp_dir().join(format!("1brc-groups-{}.txt", std::process::id()));
+    fs::write(&path, "Abha;Saudi Arabia\nZagreb;Europe\nZürich;Europe\n").unwrap();
+    let output = run("group-file", &format!("file:{}", path.display()), true);
+    fs::remove_file(&path).unwrap();
+    assert_eq!(
+        output,
+        "{Accra=3.0/3.0/3.0 {Accra=3.0/3.0/3.0}, \
+         Europe=5.0/6.0/7.0 {Zagreb=5.0/5.0/5.0, Zürich=7.0/7.0/7.0}, \
+         Nowhere=-1.0/-1.0/-1.0 {Nowhere=-1.0/-1.0/-1.0}, \
+         Saudi Arabia=1.0/1.5/2.0 {Abha=1.0/1.5/2.0}}"
+    );
+}
+
+#[test]
+fn regex() {
+    assert_eq!(
+        run("group-regex", "regex:^(A|Z)", false),
+        "{A=1.0/2.0/3.0, Nowhere=-1.0/-1.0/-1.0, Z=5.0/6.0/7.0}"
+    );
+}
+
+#[test]
+fn prefix_of_continuation_bytes() {
+    // a name that is not UTF-8 and starts with continuation bytes has no prefix to cut back to
+    let output = common::run(
+        "group-prefix-continuation",
+        &["4", "--group-by", "prefix:1"],
+        |file| file.write_all(b"\x80\x80ab;1.0\nAbha;3.0\n").unwrap(),
+    );
+    assert_eq!(output, "{=1.0/1.0/1.0, A=3.0/3.0/3.0}");
+}
@@ -0,0 +1,110 @@
+mod common;
+
+const MEASUREMENTS: &[u8] = "Abha;1.0\nA,\"b;-2.0\nAbha;2.0\n".as_bytes();
+
+fn run(name: &str, args: &[&str]) -> String {
+    let args = [&["4"], args].concat();
+    common::run(name, &args, |file| file.write_all(MEASUREMENTS).unwrap())
+}
+
+#[test]
+fn json() {
+    assert_eq!(
+        run("format-json", &["--format", "json"]),
+        r#"[{"station":"A,\"b","min":-2.0,"mean":-2.0,"max":-2.0,"count":1},"#.to_string()
+            + r#"{"station":"Abha","min":1.0,"mean":1.5,"max":2.0,"count":2}]"#
+    );
+    assert_eq!(
+        run(
+            "format-json-object",
+            &["--format", "json-object", "--stats"]
+        ),
+        r#"{"A,\"b":{"min":-2.0,"mean":-2.0,"max":-2.0,"count":1,"stddev":0.0},"#.to_string()
+            + r#""Abha":{"min":1.0,"mean":1.5,"max":2.0,"count":2,"stddev":0.5}}"#
+    );
+}
+
+#[test]
+fn delimited() {
+    assert_eq!(
+        run(
+            "format-csv",
+            &["--format", "csv", "--percentiles", "50,99.9"]
+        ),
+        "station,min,mean,max,count,p50,p99.9\n\
+         \"A,\"\"b\",-2.0,-2.0,-2.0,1,-2.0,-2.0\n\
+         Abha,1.0,1.5,2.0,2,1.0,2.0\n"
+    );
+    assert_eq!(
+        run(
+            "format-tsv",
+            &["--format", "tsv", "--top", "1", "--rank-by", "count"]
+        ),
+        "station\tmin\tmean\tmax\tcount\nAbha\t1.0\t1.5\t2.0\t2\n"
+    );
+}
+
+#[test]
+fn nested() {
+    assert_eq!(
+        run(
+            "format-nested",
+            &["--format", "csv", "--group-by", "prefix:1", "--drill-down"]
+        ),
+        "group,station,min,mean,max,count\n\
+         A,,-2.0,0.3,2.0,3\n\
+         A,\"A,\"\"b\",-2.0,-2.0,-2.0,1\n\
+         A,Abha,1.0,1.5,2.0,2\n"
+    );
+    assert_eq!(
+        run(
+            "format-nested-json",
+            &[
+                "--format",
+                "json-object",
+                "--group-by",
+                "prefix:1",
+                "--drill-down"
+            ]
+        ),
+        r#"{"A":{"min":-2.0,"mean":0.3,"max":2.0,"count":3,"stations":{"#.to_string()
+            + r#""A,\"b":{"min":-2.0,"mean":-2.0,"max":-2.0,"count":1},"#
+            + r#""Abha":{"min":1.0,"mean":1.5,"max":2.0,"count":2}}}}"#
+    );
+}
+
+#[test]
+fn output_file() {
+    let path = std::env::temp_dir().join(format!("1brc-output-{}.csv", std::process::id()));
+    std::fs::write(&path, "previous results").unwrap();
+    let path_arg = path.to_str().unwrap();
+    let stdout = run("output-file", &["--format", "csv", "--output", path_arg]);
+    let written = std::fs::read_to_string(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(stdout, "");
+    assert_eq!(
+        written,
+        "station,min,mean,max,count\n\
+         \"A,\"\"b\",-2.0,-2.0,-2.0,1\n\
+         Abha,1.0,1.5,2.0,2\n"
+    );
+}
+
+#[test]
+#[should_panic(expected = "output-missing-dir failed")]
+fn output_write_error() {
+    let path = std::env::temp_dir().join("1brc-missing-dir/results.txt");
+    run("output-missing-dir", &["--output", path.to_str().unwrap()]);
+}
+
+#[test]
+fn invalid_utf8() {
+    let output = common::run("format-invalid-utf8", &["4", "--format", "json"], |file| {
+        file.write_all(b"Ab\xffha;1.0\n").unwrap()
+    });
+    // invalid bytes are replaced, so the output is still UTF-8
+    assert_eq!(
+        output,
+        "[{\"station\":\"Ab\u{fffd}ha\",\"min\":1.0,\"mean\":1.0,\"max\":1.0,\"count\":1}]"
+    );
+}
@@ -0,0 +1,33 @@
+use one_billion_row_challange::my_hashmap::{Aggregator, StationEntry};
+
+mod common;
+
+// 999 * ROWS is past i32::MAX, which is where the old 32 bit sums wrapped around
+const ROWS: usize = 2_200_000;
+
+#[test]
+fn sums_past_i32() {
+    let output = common::run("overflow", &["4"], |file| {
+        for _ in 0..ROWS {
+            file.write_all(b"Abha;99.9\nZagreb;-99.9\n").unwrap();
+        }
+    });
+    assert_eq!(output, "{Abha=99.9/99.9/99.9, Zagreb=-99.9/-99.9/-99.9}");
+}
+
+#[test]
+fn merge_past_u32() {
+    // the totals of a station with about 2^32 rows, built directly as aggregating them is slow
+    let mut entry = StationEntry {
+        sum: i32::MAX as i64,
+        count: u32::MAX as u64,
+        min: -999,
+        max: 999,
+    };
+    let other = entry;
+    entry.merge(&other);
+    entry.add_measurement(999);
+    assert_eq!(entry.sum, 2 * i32::MAX as i64 + 999);
+    assert_eq!(entry.count, 2 * u32::MAX as u64 + 1);
+    assert_eq!((entry.min, entry.max), (-999, 999));
+}
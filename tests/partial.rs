@@ -0,0 +1,111 @@
+use std::{fs, path::PathBuf};
+
+mod common;
+
+const FIRST: &[u8] = "Abha;1.0;-3.5\nZürich;7.0;0.0\nAbha;2.0;4.5\n".as_bytes();
+const SECOND: &[u8] = "Abha;-1.0;1.0\nNowhere;5.5;2.0\n".as_bytes();
+
+fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("1brc-{name}-{}.bin", std::process::id()))
+}
+
+/// Writes a partial summary of `measurements` and returns its path.
+fn partial(name: &str, args: &[&str], measurements: &'static [u8]) -> PathBuf {
+    let path = temp_path(name);
+    let args = [
+        &["aggregate", "4", "--partial", path.to_str().unwrap()],
+        args,
+    ]
+    .concat();
+    let output = common::run(name, &args, |file| file.write_all(measurements).unwrap());
+    assert_eq!(output, "");
+    path
+}
+
+fn merge(name: &str, args: &[&str], paths: &[&PathBuf]) -> String {
+    let paths = paths.iter().map(|path| path.to_str().unwrap());
+    let args: Vec<&str> = ["merge"].into_iter().chain(args.iter().copied()).collect();
+    let args = [&args[..], &paths.collect::<Vec<_>>()].concat();
+    common::run(name, &args, |_| {})
+}
+
+#[test]
+fn merge_equals_single_run() {
+    for args in [
+        &["--columns", "2"][..],
+        &["--columns", "2", "--stats"],
+        &["--columns", "2", "--percentiles", "50,90"],
+        &[
+            "--columns",
+            "2",
+            "--percentiles",
+            "50,90",
+            "--sketch-accuracy",
+            "0.02",
+        ],
+    ] {
+        let first = partial("partial-first", args, FIRST);
+        let second = partial("partial-second", args, SECOND);
+        let merged = merge("partial-merge", &[], &[&first, &second]);
+        let full = common::run("partial-full", &[&["4"], args].concat(), |file| {
+            file.write_all(FIRST).unwrap();
+            file.write_all(SECOND).unwrap();
+        });
+        fs::remove_file(first).unwrap();
+        fs::remove_file(second).unwrap();
+        assert_eq!(merged, full, "{args:?}");
+    }
+}
+
+#[test]
+fn merge_in_steps() {
+    let first = partial("partial-step-first", &[], FIRST);
+    let second = partial("partial-step-second", &[], SECOND);
+    let both = temp_path("partial-step-both");
+    let both_arg = both.to_str().unwrap();
+    assert_eq!(
+        merge("partial-step", &["--partial", both_arg], &[&first, &second]),
+        ""
+    );
+    let output = merge("partial-step-output", &["--top", "1"], &[&both]);
+    for path in [first, second, both] {
+        fs::remove_file(path).unwrap();
+    }
+    assert_eq!(output, "{Zürich=7.0/7.0/7.0}");
+}
+
+#[test]
+#[should_panic(expected = "partial-mismatch failed")]
+fn different_statistics() {
+    let first = partial("partial-mismatch-first", &["--stats"], FIRST);
+    let second = partial("partial-mismatch-second", &[], SECOND);
+    let result = std::panic::catch_unwind(|| merge("partial-mismatch", &[], &[&first, &second]));
+    fs::remove_file(first).unwrap();
+    fs::remove_file(second).unwrap();
+    std::panic::resume_unwind(result.unwrap_err());
+}
+
+#[test]
+#[should_panic(expected = "partial-corrupt failed")]
+fn corrupt() {
+    let path = partial("partial-corrupt-write", &[], FIRST);
+    let mut bytes = fs::read(&path).unwrap();
+    bytes[20] ^= 1;
+    fs::write(&path, bytes).unwrap();
+    let result = std::panic::catch_unwind(|| merge("partial-corrupt", &[], &[&path]));
+    fs::remove_file(path).unwrap();
+    std::panic::resume_unwind(result.unwrap_err());
+}
+
+#[test]
+fn invalid_utf8() {
+    let measurements = b"Ab\xffha;1.0\nZ\xfcrich;7.0\nAb\xffha;-3.0\n";
+    let path = partial("partial-utf8", &[], measurements);
+    // merge reads the names back as they were written
+    let merged = merge("partial-utf8-merge", &[], &[&path]);
+    fs::remove_file(path).unwrap();
+    assert_eq!(
+        merged,
+        "{Ab\u{fffd}ha=-3.0/-1.0/1.0, Z\u{fffd}rich=7.0/7.0/7.0}"
+    );
+}
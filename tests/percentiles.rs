@@ -0,0 +1,83 @@
+mod common;
+
+#[test]
+fn exact_percentiles() {
+    let output = common::run(
+        "percentiles",
+        &["4", "--percentiles", "50,90,99.9"],
+        |file| {
+            for tenths in 1..=10 {
+                writeln!(file, "Abha;{}.{}", tenths / 10, tenths % 10).unwrap();
+            }
+            // an unknown name, so the overflow map is covered too
+            file.write_all(b"Nowhere;-5.0\nNowhere;7.5\nNowhere;-5.0\n")
+                .unwrap();
+        },
+    );
+    assert_eq!(
+        output,
+        "{Abha=0.1/0.6/1.0/0.5/0.9/1.0, Nowhere=-5.0/-0.8/7.5/-5.0/7.5/7.5}"
+    );
+}
+
+#[test]
+fn sketch_percentiles() {
+    let write_measurements = |file: &mut dyn std::io::Write| {
+        for tenths in -999..=999 {
+            writeln!(file, "Abha;{:.1}", tenths as f64 / 10.0).unwrap();
+        }
+    };
+    let exact = common::run(
+        "sketch-exact",
+        &["4", "--percentiles", "50,95,99"],
+        write_measurements,
+    );
+    assert_eq!(exact, "{Abha=-99.9/0.0/99.9/0.0/90.0/98.0}");
+
+    let args = [
+        "4",
+        "--percentiles",
+        "50,95,99",
+        "--sketch-accuracy",
+        "0.01",
+    ];
+    let sketch = common::run("sketch", &args, write_measurements);
+    let values: Vec<f64> = sketch
+        .trim_start_matches("{Abha=")
+        .trim_end_matches('}')
+        .split('/')
+        .map(|value| value.parse().unwrap())
+        .collect();
+    assert_eq!(values[..3], [-99.9, 0.0, 99.9]);
+    for (value, expected) in values[3..].iter().zip([0.0, 90.0, 98.0]) {
+        // within the accuracy, and then rounded to the one decimal of the measurements
+        assert!(
+            (value - expected).abs() <= expected * 0.01 + 0.05,
+            "{sketch}"
+        );
+    }
+}
+
+#[test]
+fn sketch_decimals() {
+    let args = ["4", "--percentiles", "50,99", "--sketch-accuracy", "0.01"];
+    let output = common::run("sketch-decimals", &args, |file| {
+        file.write_all(b"Abha;1234.567\nAbha;-0.001\nAbha;250000.25\nAbha;-1500\n")
+            .unwrap();
+    });
+    let values: Vec<f64> = output
+        .trim_start_matches("{Abha=")
+        .trim_end_matches('}')
+        .split('/')
+        .map(|value| value.parse().unwrap())
+        .collect();
+    // the most decimals of any measurement, so the extremes are exact
+    assert_eq!(values[..3], [-1500.000, 62433.704, 250000.250], "{output}");
+    for (value, expected) in values[3..].iter().zip([-0.001, 250000.25]) {
+        assert!(
+            (value - expected).abs() <= expected.abs() * 0.01,
+            "{output}"
+        );
+    }
+    assert!(output.starts_with("{Abha=-1500.000/"), "{output}");
+}
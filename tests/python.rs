@@ -0,0 +1,36 @@
+#![cfg(feature = "python")]
+
+use std::{fs, process::Command};
+
+/// Runs tests/python/brc_test.py with the library as the `brc` module on `measurements`.
+fn run_python_test(measurements: &str) -> String {
+    let dir = std::env::temp_dir().join(format!("1brc-python-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("measurements.txt"), measurements).unwrap();
+    // cargo builds the library next to the tests, and Python imports it by its module name
+    let test = std::env::current_exe().unwrap();
+    let library = test.with_file_name("libone_billion_row_challange.so");
+    fs::copy(library, dir.join("brc.so")).unwrap();
+    let output = Command::new("python3")
+        .arg("tests/python/brc_test.py")
+        .arg(dir.join("measurements.txt"))
+        .env("PYTHONPATH", &dir)
+        .output()
+        .unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+    assert!(
+        output.status.success(),
+        "brc_test.py failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn python_module() {
+    assert_eq!(
+        run_python_test("Abha;1.0\nZürich;-7.5\nAbha;2.5\nAbha;-4.0\n"),
+        "Abha;-4.0;-0.2000;2.5;3\n\
+         Zürich;-7.5;-7.5000;-7.5;1\n"
+    );
+}
@@ -0,0 +1,30 @@
+"""Aggregates the file given as the only argument through the brc module, in every way it
+offers, and prints every station as `name;min;mean;max;count`. Run by tests/python.rs."""
+import sys
+
+import brc
+
+path = sys.argv[1]
+try:
+    brc.aggregate("does-not-exist.txt")
+    raise AssertionError("aggregating a missing file succeeded")
+except FileNotFoundError:
+    pass
+
+records = brc.aggregate(path, threads=2)
+with open(path, "rb") as file:
+    data = file.read()
+assert brc.aggregate_bytes(data) == records
+assert brc.aggregate_bytes(bytearray(data), threads=1) == records
+assert brc.aggregate_bytes(memoryview(data)) == records
+assert brc.aggregate_bytes(memoryview(bytearray(data)).toreadonly()) == records
+# an incomplete last line is ignored
+assert brc.aggregate_bytes(data + b"Abha;99") == records
+assert brc.aggregate_bytes(b"") == []
+
+columns = brc.aggregate(path, columns=True)
+assert list(columns) == ["station", "min", "mean", "max", "count"]
+assert [dict(zip(columns, row)) for row in zip(*columns.values())] == records
+
+for record in records:
+    print("{station};{min:.1f};{mean:.4f};{max:.1f};{count}".format(**record))
@@ -0,0 +1,55 @@
+mod common;
+
+const MEASUREMENTS: &[u8] =
+    "Abha;1.0\nAccra;1.4\nZagreb;5.0\nZürich;7.0\nNowhere;-1.0\nAbha;2.0\nAccra;1.5\n".as_bytes();
+
+fn run(name: &str, args: &[&str]) -> String {
+    let args = [&["4"], args].concat();
+    common::run(name, &args, |file| file.write_all(MEASUREMENTS).unwrap())
+}
+
+#[test]
+fn top_and_bottom() {
+    assert_eq!(
+        run("rank-top", &["--top", "2"]),
+        "{Zürich=7.0/7.0/7.0, Zagreb=5.0/5.0/5.0}"
+    );
+    // Accra's mean of 1.45 prints as 1.5 but still ranks below Abha's 1.5
+    assert_eq!(
+        run("rank-bottom", &["--bottom", "3", "--rank-by", "mean"]),
+        "{Nowhere=-1.0/-1.0/-1.0, Accra=1.4/1.5/1.5, Abha=1.0/1.5/2.0}"
+    );
+    assert_eq!(
+        run("rank-all", &["--rank-by", "min", "--bottom", "10"]),
+        "{Nowhere=-1.0/-1.0/-1.0, Abha=1.0/1.5/2.0, Accra=1.4/1.5/1.5, \
+         Zagreb=5.0/5.0/5.0, Zürich=7.0/7.0/7.0}"
+    );
+}
+
+#[test]
+fn ties_by_name() {
+    assert_eq!(
+        run("rank-count", &["--top", "2", "--rank-by", "count"]),
+        "{Abha=1.0/1.5/2.0, Accra=1.4/1.5/1.5}"
+    );
+}
+
+#[test]
+fn ranked_groups() {
+    assert_eq!(
+        run(
+            "rank-groups",
+            &[
+                "--group-by",
+                "prefix:1",
+                "--drill-down",
+                "--top",
+                "2",
+                "--rank-by",
+                "max"
+            ]
+        ),
+        "{Z=5.0/6.0/7.0 {Zagreb=5.0/5.0/5.0, Zürich=7.0/7.0/7.0}, \
+         A=1.0/1.5/2.0 {Abha=1.0/1.5/2.0, Accra=1.4/1.5/1.5}}"
+    );
+}
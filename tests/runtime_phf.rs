@@ -0,0 +1,64 @@
+use std::fs;
+
+use one_billion_row_challange::my_phf::Phf;
+
+mod common;
+
+/// `count` different names of 4 to 20 letters.
+fn random_names(count: usize) -> Vec<String> {
+    let mut state = 0x9e37_79b9_7f4a_7c15u64;
+    let mut next = move || {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        state
+    };
+    let mut names = Vec::new();
+    while names.len() < count {
+        let len = 4 + next() % 17;
+        let name: String = (0..len)
+            .map(|_| (b'a' + (next() % 26) as u8) as char)
+            .collect();
+        if !names.contains(&name) {
+            names.push(name);
+        }
+    }
+    names
+}
+
+#[test]
+fn build() {
+    let as_bytes = |names: Vec<String>| names.into_iter().map(String::into_bytes).collect();
+    let phf = Phf::build(as_bytes(random_names(500))).unwrap();
+    assert_eq!(phf.names().count(), 500);
+    // too many for a table within the size limit, which is found out without searching
+    assert!(Phf::build(as_bytes(random_names(5000))).is_err());
+}
+
+#[test]
+fn too_many_names() {
+    let names = random_names(5000);
+    let names_path =
+        std::env::temp_dir().join(format!("1brc-runtime-phf-{}.txt", std::process::id()));
+    fs::write(&names_path, names.join("\n")).unwrap();
+    let write_measurements = |file: &mut dyn std::io::Write| {
+        for (i, name) in names.iter().enumerate() {
+            writeln!(file, "{name};{}.{}\n{name};-1.5", i % 100, i % 10).unwrap();
+        }
+    };
+    let expected = common::run("runtime-phf-expected", &["4"], write_measurements);
+    // every station is kept in the overflow map instead
+    let named = common::run(
+        "runtime-phf-names",
+        &["4", "--names", names_path.to_str().unwrap()],
+        write_measurements,
+    );
+    let sampled = common::run(
+        "runtime-phf-sampled",
+        &["4", "--sample-names"],
+        write_measurements,
+    );
+    fs::remove_file(names_path).unwrap();
+    assert_eq!(named, expected);
+    assert_eq!(sampled, expected);
+}
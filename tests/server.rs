@@ -0,0 +1,191 @@
+use std::{
+    fs,
+    io::{Read, Write},
+    os::unix::net::UnixStream,
+    path::Path,
+    process::Command,
+    thread,
+    time::Duration,
+};
+
+mod common;
+
+/// Sends a GET request and returns the response, once the server is listening.
+fn get(socket: &Path, target: &str) -> String {
+    let mut stream = loop {
+        match UnixStream::connect(socket) {
+            Ok(stream) => break stream,
+            Err(_) => thread::sleep(Duration::from_millis(10)),
+        }
+    };
+    write!(stream, "GET {target} HTTP/1.1\r\nHost: localhost\r\n\r\n").unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    response
+}
+
+/// The body of a response with `status`.
+fn body(response: &str, status: &str) -> String {
+    let (head, body) = response.split_once("\r\n\r\n").unwrap();
+    assert!(
+        head.starts_with(&format!("HTTP/1.1 {status}")),
+        "{response}"
+    );
+    body.to_string()
+}
+
+#[test]
+fn serve() {
+    let dir = std::env::temp_dir().join(format!("1brc-serve-{}", std::process::id()));
+    let watched = dir.join("incoming");
+    fs::create_dir_all(&watched).unwrap();
+    fs::write(dir.join("measurements.txt"), "Abha;1.0\nZürich;7.0\n").unwrap();
+    fs::write(watched.join("a.txt"), "Abha;3.0\nNowhere;-2.5\n").unwrap();
+    let socket = dir.join("query.sock");
+    let mut child = Command::new(env!("CARGO_BIN_EXE_one-billion-row-challange"))
+        .arg("4")
+        .arg("--serve")
+        .arg(format!("unix:{}", socket.display()))
+        .args(["--watch", "incoming"])
+        .current_dir(&dir)
+        .spawn()
+        .unwrap();
+
+    let responses = [
+        body(&get(&socket, "/all?format=brace"), "200"),
+        body(&get(&socket, "/station/Z%C3%BCrich"), "200"),
+        body(&get(&socket, "/top?metric=max&k=2&format=csv"), "200"),
+        body(&get(&socket, "/bottom?k=1&format=json-object"), "200"),
+        body(&get(&socket, "/station/Hamburg"), "404"),
+        body(&get(&socket, "/all?format=xml"), "400"),
+    ];
+    // a new file, moved into place once written
+    fs::write(watched.join(".b.txt"), "Hamburg;12.0\n").unwrap();
+    fs::rename(watched.join(".b.txt"), watched.join("b.txt")).unwrap();
+    let added = loop {
+        let response = get(&socket, "/station/Hamburg");
+        if !response.starts_with("HTTP/1.1 404") {
+            break body(&response, "200");
+        }
+        thread::sleep(Duration::from_millis(10));
+    };
+    child.kill().unwrap();
+    child.wait().unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(
+        responses,
+        [
+            "{Abha=1.0/2.0/3.0, Nowhere=-2.5/-2.5/-2.5, Zürich=7.0/7.0/7.0}",
+            r#"[{"station":"Zürich","min":7.0,"mean":7.0,"max":7.0,"count":1}]"#,
+            "station,min,mean,max,count\n\
+             Zürich,7.0,7.0,7.0,1\n\
+             Abha,1.0,2.0,3.0,2\n",
+            r#"{"Nowhere":{"min":-2.5,"mean":-2.5,"max":-2.5,"count":1}}"#,
+            "not found: /station/Hamburg",
+            "unknown output format: xml",
+        ]
+    );
+    assert_eq!(
+        added,
+        r#"[{"station":"Hamburg","min":12.0,"mean":12.0,"max":12.0,"count":1}]"#
+    );
+}
+
+/// Lines of a multiple of any page size, so the margin after them would be the start of a page.
+fn page_sized_lines() -> String {
+    let size = 1 << 16;
+    let oslo = "Oslo;12.0\n".repeat(6550);
+    // an unknown station, whose name fills the file up to the size
+    let lines = format!("{};1.0\n{oslo}", "A".repeat(size - oslo.len() - 5));
+    assert_eq!(lines.len(), size);
+    lines
+}
+
+#[test]
+fn page_sized_file() {
+    let dir = std::env::temp_dir().join(format!("1brc-serve-page-{}", std::process::id()));
+    let watched = dir.join("incoming");
+    fs::create_dir_all(&watched).unwrap();
+    fs::write(dir.join("measurements.txt"), "Abha;1.0\n").unwrap();
+    fs::write(watched.join("page.txt"), page_sized_lines()).unwrap();
+    let socket = dir.join("query.sock");
+    let mut child = Command::new(env!("CARGO_BIN_EXE_one-billion-row-challange"))
+        .arg("4")
+        .arg("--serve")
+        .arg(format!("unix:{}", socket.display()))
+        .args(["--watch", "incoming"])
+        .current_dir(&dir)
+        .spawn()
+        .unwrap();
+    // the socket is bound once the files in the directory are aggregated
+    while !socket.exists() {
+        if let Some(status) = child.try_wait().unwrap() {
+            panic!("the server exited: {status}");
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+    let response = body(&get(&socket, "/station/Oslo?format=brace"), "200");
+    child.kill().unwrap();
+    child.wait().unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(response, "{Oslo=12.0/12.0/12.0}");
+}
+
+#[test]
+fn page_sized_measurements() {
+    let lines = page_sized_lines();
+    let checkpoint =
+        std::env::temp_dir().join(format!("1brc-page-checkpoint-{}.bin", std::process::id()));
+    let runs = [
+        ("page-plain", &["4", "--include", "Oslo"][..]),
+        (
+            "page-sampled",
+            &["4", "--include", "Oslo", "--sample-names"],
+        ),
+        (
+            "page-checkpoint",
+            &[
+                "4",
+                "--include",
+                "Oslo",
+                "--checkpoint",
+                checkpoint.to_str().unwrap(),
+            ],
+        ),
+    ];
+    for (name, args) in runs {
+        let output = common::run(name, args, |file| file.write_all(lines.as_bytes()).unwrap());
+        assert_eq!(output, "{Oslo=12.0/12.0/12.0}", "{name}");
+    }
+    fs::remove_file(checkpoint).unwrap();
+}
+
+#[test]
+fn output_options() {
+    let dir = std::env::temp_dir().join(format!("1brc-serve-options-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("measurements.txt"), "Abha;1.0\n").unwrap();
+    let options = [
+        &["--format", "csv"][..],
+        &["--sort", "max"],
+        &["--top", "3"],
+    ];
+    let stderrs: Vec<String> = options
+        .iter()
+        .map(|option| {
+            let output = Command::new(env!("CARGO_BIN_EXE_one-billion-row-challange"))
+                .args(["4", "--serve", "unix:query.sock"])
+                .args(*option)
+                .current_dir(&dir)
+                .output()
+                .unwrap();
+            String::from_utf8_lossy(&output.stderr).into_owned()
+        })
+        .collect();
+    fs::remove_dir_all(&dir).unwrap();
+    for (option, stderr) in options.iter().zip(stderrs) {
+        let message = format!("--serve cannot be combined with {}", option[0]);
+        assert!(stderr.contains(&message), "{stderr}");
+    }
+}
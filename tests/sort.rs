@@ -0,0 +1,54 @@
+mod common;
+
+const MEASUREMENTS: &[u8] =
+    "Zagreb;1.0\nabha;2.0\nAbéché;3.0\nİzmir;4.0\nAbeche;6.0\nizmir;1.0\n".as_bytes();
+
+fn run(name: &str, args: &[&str]) -> String {
+    let args = [&["4"], args].concat();
+    common::run(name, &args, |file| file.write_all(MEASUREMENTS).unwrap())
+}
+
+/// Just the station names, in the order they were printed.
+fn names(output: &str) -> Vec<&str> {
+    let output = output.trim_start_matches('{').trim_end_matches('}');
+    output
+        .split(", ")
+        .map(|r| r.split('=').next().unwrap())
+        .collect()
+}
+
+#[test]
+fn name_orders() {
+    let bytes = run("sort-bytes", &["--sort", "bytes"]);
+    assert_eq!(bytes, run("sort-default", &[]));
+    assert_eq!(
+        names(&bytes),
+        ["Abeche", "Abéché", "Zagreb", "abha", "izmir", "İzmir"]
+    );
+    assert_eq!(
+        names(&run("sort-case", &["--sort", "case-insensitive"])),
+        ["Abeche", "abha", "Abéché", "izmir", "İzmir", "Zagreb"]
+    );
+    assert_eq!(
+        names(&run("sort-locale", &["--sort", "locale"])),
+        ["Abeche", "Abéché", "abha", "izmir", "İzmir", "Zagreb"]
+    );
+    assert_eq!(
+        names(&run("sort-locale-desc", &["--sort", "locale:desc"])),
+        ["Zagreb", "İzmir", "izmir", "abha", "Abéché", "Abeche"]
+    );
+}
+
+#[test]
+fn metric_order() {
+    // ties stay in name order in both directions
+    assert_eq!(
+        names(&run("sort-mean", &["--sort", "mean:desc"])),
+        ["Abeche", "İzmir", "Abéché", "abha", "Zagreb", "izmir"]
+    );
+    // --top picks the stations, --sort orders them
+    assert_eq!(
+        names(&run("sort-top", &["--top", "3", "--sort", "locale"])),
+        ["Abeche", "Abéché", "İzmir"]
+    );
+}
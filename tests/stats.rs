@@ -0,0 +1,18 @@
+mod common;
+
+#[test]
+fn standard_deviation() {
+    let output = common::run("stats", &["4", "--stats"], |file| {
+        // known and unknown names, so both the PHF entries and the overflow map are covered
+        file.write_all(
+            b"Abha;1.0\nZagreb;10.0\nNowhere;-1.0\nAbha;3.0\nZagreb;10.0\nNowhere;0.0\n\
+              Zagreb;10.0\nNowhere;1.0\nWau;-99.9\nWau;99.9\n",
+        )
+        .unwrap();
+    });
+    assert_eq!(
+        output,
+        "{Abha=1.0/2.0/3.0/1.0, Nowhere=-1.0/0.0/1.0/0.8, Wau=-99.9/0.0/99.9/99.9, \
+         Zagreb=10.0/10.0/10.0/0.0}"
+    );
+}
@@ -0,0 +1,61 @@
+mod common;
+
+const MEASUREMENTS: &[u8] = b"\
+Abha;2024-03-05T12:10:00Z;1.0
+Llanfairpwllgwyngyllgogerychwyndrobwllllantysiliogogogoch;2024-03-05T12:00:00Z;7.0
+Abha;2024-03-05T12:59:59.999Z;3.0
+Nowhere;2024-02-29 23:30:00;0.5
+Abha;2024-03-05T13:00:00+01:00;5.0
+Abha;1709647200;-2.0
+Nowhere;2024-03-01;-0.5
+Nowhere;-1800;9.9
+Abha;2024-03-05T09:30-05:30;-4.0
+";
+
+#[test]
+fn hourly_buckets() {
+    let output = common::run(
+        "time-buckets-hour",
+        &["4", "--time-buckets", "hour"],
+        |file| file.write_all(MEASUREMENTS).unwrap(),
+    );
+    assert_eq!(
+        output,
+        "{Abha={2024-03-05T12=1.0/3.0/5.0, 2024-03-05T14=-2.0/-2.0/-2.0, \
+         2024-03-05T15=-4.0/-4.0/-4.0}, \
+         Llanfairpwllgwyngyllgogerychwyndrobwllllantysiliogogogoch={2024-03-05T12=7.0/7.0/7.0}, \
+         Nowhere={1969-12-31T23=9.9/9.9/9.9, 2024-02-29T23=0.5/0.5/0.5, \
+         2024-03-01T00=-0.5/-0.5/-0.5}}"
+    );
+}
+
+#[test]
+fn monthly_buckets() {
+    let args = ["4", "--time-buckets", "month", "--stats"];
+    let output = common::run("time-buckets-month", &args, |file| {
+        file.write_all(MEASUREMENTS).unwrap()
+    });
+    assert_eq!(
+        output,
+        "{Abha={2024-03=-4.0/0.6/5.0/3.3}, \
+         Llanfairpwllgwyngyllgogerychwyndrobwllllantysiliogogogoch={2024-03=7.0/7.0/7.0/0.0}, \
+         Nowhere={1969-12=9.9/9.9/9.9/0.0, 2024-02=0.5/0.5/0.5/0.0, 2024-03=-0.5/-0.5/-0.5/0.0}}"
+    );
+}
+
+#[test]
+fn names_not_utf8() {
+    let output = common::run(
+        "time-buckets-not-utf8",
+        &["4", "--time-buckets", "day"],
+        |file| {
+            file.write_all(b"Z\xfcrich;2024-03-05;1.0\nZ\xfcrich;2024-03-06;2.0\n")
+                .unwrap()
+        },
+    );
+    // both buckets belong to one station, even though its name is shown lossily
+    assert_eq!(
+        output,
+        "{Z\u{fffd}rich={2024-03-05=1.0/1.0/1.0, 2024-03-06=2.0/2.0/2.0}}"
+    );
+}
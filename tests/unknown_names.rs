@@ -0,0 +1,45 @@
+use one_billion_row_challange::my_phf::Phf;
+
+mod common;
+
+/// A name that is not one of the stations but hashes to the slot of `station`.
+fn colliding_name(phf: &Phf, station: &[u8]) -> String {
+    let index_of = |name: &[u8]| {
+        // the hash reads past the name
+        let mut padded = name.to_vec();
+        padded.resize(name.len() + 64, 0);
+        phf.get_name_index(&padded[..name.len()])
+    };
+    let slot = index_of(station);
+    // letters that differ at every position, wherever the hash samples the name
+    (1u64..)
+        .map(|i| {
+            let mut state = i.wrapping_mul(0x9e37_79b9_7f4a_7c15);
+            let letters = (0..24).map(|_| {
+                state ^= state >> 29;
+                state = state.wrapping_mul(0xbf58_476d_1ce4_e5b9);
+                (b'a' + (state >> 40) as u8 % 26) as char
+            });
+            format!("X{}", letters.collect::<String>())
+        })
+        .find(|name| index_of(name.as_bytes()) == slot)
+        .unwrap()
+}
+
+#[test]
+fn collision_with_a_station() {
+    let phf = Phf::station_names();
+    let unknown = colliding_name(&phf, b"Hamburg");
+    assert!(phf.names().all(|(name, _)| name != unknown.as_bytes()));
+    let output = common::run("unknown-names", &["4"], |file| {
+        write!(
+            file,
+            "Hamburg;12.0\n{unknown};-5.0\nHamburg;8.0\n{unknown};-7.0\n{unknown};0.0\n"
+        )
+        .unwrap();
+    });
+    assert_eq!(
+        output,
+        format!("{{Hamburg=8.0/10.0/12.0, {unknown}=-7.0/-4.0/0.0}}")
+    );
+}